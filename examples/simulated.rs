@@ -0,0 +1,45 @@
+use std::error::Error;
+use std::sync::Arc;
+use tap_sdk::types::device::EventSource;
+use tap_sdk::transport::SimulatedTap;
+use tap_sdk::types::input_type::InputMode;
+use tap_sdk::types::air_gestures::AirGestures;
+use tap_sdk::types::tap_code::TapCode;
+
+fn on_gesture(source: EventSource, gesture: AirGestures) {
+    println!("{} gesture {:?}", source, gesture);
+}
+
+fn on_tapped(source: EventSource, tapcode: TapCode) {
+    println!("{} tapped {}", source, tapcode);
+}
+
+fn on_moused(source: EventSource, vx: i16, vy: i16, proximity: bool) {
+    println!("{} mouse movement: {}, {}, {}", source, vx, vy, proximity);
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn Error>> {
+    let simulated_tap = Arc::new(SimulatedTap::new());
+    let mut tap_client = tap_sdk::Client::with_transport(simulated_tap.clone());
+    tap_client.run().await?;
+
+    let _air_gesture_subscription = tap_client.register_air_gesture_events(on_gesture).await?;
+    let _tap_subscription = tap_client.register_tap_events(on_tapped).await?;
+    let _mouse_subscription = tap_client.register_mouse_events(on_moused).await?;
+
+    tap_client.set_input_mode(InputMode::Controller, None).await?;
+    tap_client.send_vibration_sequence(vec![100, 200]).await?;
+
+    simulated_tap.push_tap(0b00001);
+    simulated_tap.push_tap(0b10010);
+    simulated_tap.push_mouse(12, -4, false);
+    simulated_tap.push_air_gesture(AirGestures::UpOneFinger);
+    tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
+
+    for (characteristic, command) in simulated_tap.written_commands() {
+        println!("{:?} <- {:?}", characteristic, command);
+    }
+
+    Ok(())
+}
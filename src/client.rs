@@ -1,230 +1,665 @@
-use btleplug::api::{BDAddr, Central, Manager as _, Peripheral, ScanFilter};
-use btleplug::platform::{Manager, Peripheral as PlatformPeripheral};
-use tokio::time::sleep;
-use std::error::Error;
+use btleplug::api::BDAddr;
+use btleplug::platform::{Adapter, Manager};
+use tokio::time::{sleep, timeout};
+use futures::{future, StreamExt};
+use std::collections::HashMap;
+use std::future::Future;
+use std::path::{Path, PathBuf};
 use std::time::Duration;
 use uuid::Uuid;
-use std::sync::Arc;
+use std::sync::{Arc, Mutex as StdMutex};
 use tokio::sync::Mutex;
+use tokio::task::JoinHandle;
 
-use crate::event_manager::EventManager;
+use crate::client_builder::ClientBuilder;
+use crate::discovery::{find_peripheral, select_with, DeviceSelector, DiscoveryOptions};
+use crate::error::TapError;
+use crate::event_manager::{EventManager, Subscription};
+use crate::reconnect::ReconnectPolicy;
+use crate::recorder::SessionRecorder;
+use crate::transport::{BleTransport, ReplaySpeed, ReplayTransport, TapTransport};
 use crate::types::input_type::{InputType, InputMode};
+use crate::types::firmware::{FirmwareFeature, FirmwareVersion};
+use crate::types::model::{Capabilities, TapModel};
 use crate::types::mouse_mode::MouseModes;
 use crate::types::air_gestures::AirGestures;
+use crate::types::device::{ConnectionState, DeviceId, EventSource};
+use crate::types::device_info::DeviceInfo;
+use crate::types::event::{decode_battery, EventStream, TapEvent};
+use crate::types::raw_sensors::RawSensorPacket;
+use crate::types::sensitivity::RawSensorConfig;
+use crate::types::tap_code::TapCode;
+use crate::types::uuid::characteristics::Characteristic;
+
+// The last mode settings sent to the device, re-applied after a reconnect,
+// and the recorder every written command goes to, background tasks included.
+#[derive(Debug, Clone)]
+struct DeviceState {
+    input_mode: InputMode,
+    sensitivity: Option<Vec<u8>>,
+    // `None` until an input type is sent, so it is never sent to firmware without support.
+    input_type: Option<InputType>,
+    mouse_mode: Option<MouseModes>,
+    raw_sensor_config: RawSensorConfig,
+    recorder: Option<Arc<SessionRecorder>>,
+}
+
+impl DeviceState {
+    fn commands(&self) -> Vec<(Characteristic, Vec<u8>)> {
+        let mut commands = vec![(Characteristic::TapMode, self.input_mode.to_command(self.sensitivity.clone()))];
+        if let Some(input_type) = self.input_type {
+            commands.push((Characteristic::TapMode, input_type.to_command()));
+        }
+        if let Some(mouse_mode) = self.mouse_mode {
+            commands.push((Characteristic::TapMode, mouse_mode.to_command()));
+        }
+        commands
+    }
+}
+
+impl Default for DeviceState {
+    fn default() -> Self {
+        DeviceState {
+            input_mode: InputMode::Text,
+            sensitivity: None,
+            input_type: None,
+            mouse_mode: None,
+            raw_sensor_config: RawSensorConfig::default(),
+            recorder: None,
+        }
+    }
+}
+
+pub const DEFAULT_AUTO_REFRESH_INTERVAL: Duration = Duration::from_secs(10);
+// Shorter intervals, zero included, are raised to this.
+pub const MIN_AUTO_REFRESH_INTERVAL: Duration = Duration::from_millis(100);
+
+pub(crate) fn clamp_auto_refresh_interval(interval: Option<Duration>) -> Option<Duration> {
+    interval.map(|interval| interval.max(MIN_AUTO_REFRESH_INTERVAL))
+}
+
+// Tap firmware falls back to text mode unless the mode is sent again
+// every few seconds, so the last mode and input type are rewritten on a timer.
+#[derive(Debug)]
+struct AutoRefresh {
+    interval: Option<Duration>,
+    task: Option<JoinHandle<()>>,
+}
+
+impl AutoRefresh {
+    fn start(&mut self, transport: Arc<dyn TapTransport>, state: Arc<StdMutex<DeviceState>>) {
+        self.stop();
+        if let Some(interval) = self.interval {
+            self.task = Some(tokio::spawn(refresh_input_mode(transport, state, interval)));
+        }
+    }
+
+    fn is_running(&self) -> bool {
+        self.task.as_ref().is_some_and(|task| !task.is_finished())
+    }
+
+    fn stop(&mut self) {
+        if let Some(task) = self.task.take() {
+            task.abort();
+        }
+    }
+}
 
 // [markdown]
 // `Client` class 
 #[derive(Debug)]
 pub struct Client {
-    bluetooth_manager: btleplug::platform::Manager,
+    bluetooth_manager: Option<btleplug::platform::Manager>,
     event_manager: Arc<Mutex<EventManager>>,
     address: Option<BDAddr>,
-    peripheral: Option<Arc<Mutex<PlatformPeripheral>>>,
-    input_mode: Option<InputMode>,
-    input_type: Option<InputType>,
-    auto_refresh_running: bool,
+    transport: Option<Arc<dyn TapTransport>>,
+    state: Arc<StdMutex<DeviceState>>,
+    reconnect_policy: Option<ReconnectPolicy>,
+    reconnect_task: Option<JoinHandle<()>>,
+    running: bool,
+    auto_refresh: Arc<StdMutex<AutoRefresh>>,
+    discovery: DiscoveryOptions,
+    connect_timeout: Option<Duration>,
+    initial_mode: Option<InputMode>,
+    initial_input_type: Option<InputType>,
+    model: TapModel,
+    firmware_version: Option<FirmwareVersion>,
+    min_firmware_versions: HashMap<FirmwareFeature, FirmwareVersion>,
+    record_to: Option<PathBuf>,
+    replay: Option<Arc<ReplayTransport>>,
 }
 
 impl Client {
-    pub async fn new(address: Option<BDAddr>) -> Result<Self, Box<dyn Error>> {
-        let manager = Manager::new().await?;
-        let event_manager = Arc::new(Mutex::new(EventManager::new()));
-        let mut client = Client {
-            bluetooth_manager: manager,
-            address: None,
-            event_manager,
-            peripheral: None,
-            input_mode: Some(InputMode::Text),
-            input_type: Some(InputType::Auto),
-            auto_refresh_running: false,
-        };
+    pub async fn new(address: Option<BDAddr>) -> Result<Self, TapError> {
+        match address {
+            Some(address) => Client::with_device(DeviceSelector::Address(address)).await,
+            None => Client::with_device(DeviceSelector::FirstFound).await,
+        }
+    }
 
-        let address = if address.is_none() {
-            match client.get_mac_address().await {
-                Ok(mac_address) => mac_address,
-                Err(error) => {
-                    panic!("Error during taking Tap device address: {:?}", error);
-                }
-            }
-        } else {
-            address.unwrap()
+    // The device only has to be advertising, `run` connects to it.
+    pub async fn with_device(selector: DeviceSelector) -> Result<Self, TapError> {
+        ClientBuilder::new().device(selector).build().await
+    }
+
+    pub fn with_transport(transport: Arc<dyn TapTransport>) -> Self {
+        Client::from_settings(ClientBuilder::new().transport(transport), None)
+    }
+
+    // A client for a session recorded with `start_recording`. Register the
+    // listeners after `run`, then `replay` plays the session through them.
+    pub fn from_recording(path: impl AsRef<Path>, speed: ReplaySpeed) -> Result<Self, TapError> {
+        let replay = Arc::new(ReplayTransport::open(path)?.with_speed(speed));
+        let mut client = Client::with_transport(Arc::clone(&replay) as Arc<dyn TapTransport>);
+        client.replay = Some(replay);
+        Ok(client)
+    }
+
+    pub fn builder() -> ClientBuilder {
+        ClientBuilder::new()
+    }
+
+    pub(crate) async fn from_builder(builder: ClientBuilder) -> Result<Self, TapError> {
+        if builder.transport.is_some() {
+            return Ok(Client::from_settings(builder, None));
+        }
+
+        let manager = Manager::new().await?;
+        let selector = builder.device.clone();
+        let mut client = Client::from_settings(builder, Some(manager));
+        let address = match selector {
+            DeviceSelector::Address(address) => address,
+            selector => client.select_address(&selector).await?,
         };
 
         client.address = Some(address);
 
-        return Ok(client);
+        Ok(client)
     }
 
-    async fn get_mac_address(&self) -> Result<BDAddr, Box<dyn Error>> {
-        let adapter_list = self.bluetooth_manager.adapters().await?;
-        if adapter_list.is_empty() {
-            return Err("No Bluetooth adapters found".into());
+    fn from_settings(builder: ClientBuilder, bluetooth_manager: Option<Manager>) -> Self {
+        Client {
+            bluetooth_manager,
+            address: None,
+            event_manager: Arc::new(Mutex::new(EventManager::new())),
+            transport: builder.transport,
+            state: Arc::default(),
+            reconnect_policy: builder.reconnect_policy,
+            reconnect_task: None,
+            running: false,
+            auto_refresh: Arc::new(StdMutex::new(AutoRefresh {
+                interval: builder.auto_refresh_interval,
+                task: None,
+            })),
+            discovery: builder.discovery,
+            connect_timeout: builder.connect_timeout,
+            initial_mode: builder.initial_mode,
+            initial_input_type: builder.initial_input_type,
+            model: TapModel::Unknown,
+            firmware_version: None,
+            min_firmware_versions: builder.min_firmware_versions,
+            record_to: builder.record_to,
+            replay: None,
         }
+    }
 
-        for adapter in adapter_list.iter() {
-            println!("Starting scan...");
-            adapter
-                .start_scan(ScanFilter::default())
-                .await
-                .expect("Can't scan BLE adapter for connected devices...");
+    async fn select_address(&self, selector: &DeviceSelector) -> Result<BDAddr, TapError> {
+        let bluetooth_manager = self.bluetooth_manager.as_ref().ok_or(TapError::NoAdapter)?;
+        let tap = select_with(bluetooth_manager, selector, &self.discovery).await?;
+        Ok(tap.address)
+    }
 
-            sleep(Duration::from_secs(2)).await;
+    pub fn device_id(&self) -> Option<DeviceId> {
+        match &self.transport {
+            Some(transport) => Some(DeviceId::from(transport.address())),
+            None => self.address.map(DeviceId::from),
+        }
+    }
+
+    async fn find_peripheral(&self) -> Result<(Adapter, btleplug::platform::Peripheral), TapError> {
+        let bluetooth_manager = self.bluetooth_manager.as_ref().ok_or(TapError::NoAdapter)?;
+        let address = self.address.ok_or(TapError::DeviceNotFound)?;
+        if let Some(found) = find_peripheral(bluetooth_manager, address, &self.discovery).await? {
+            return Ok(found);
+        }
 
-            let peripherals = adapter.peripherals().await?;
-    
-            if peripherals.is_empty() {
-                return Err("No Bluetooth devices found".into());
+        // Not seen by the adapter yet, scan for it while it advertises.
+        select_with(bluetooth_manager, &DeviceSelector::Address(address), &self.discovery).await?;
+        find_peripheral(bluetooth_manager, address, &self.discovery).await?.ok_or(TapError::DeviceNotFound)
+    }
+
+    pub async fn run (&mut self) -> Result<(), TapError> {
+        let transport = match &self.transport {
+            Some(transport) => Arc::clone(transport),
+            None => {
+                let (adapter, peripheral) = self.find_peripheral().await?;
+                let transport: Arc<dyn TapTransport> = Arc::new(BleTransport::new(adapter, peripheral));
+                self.transport = Some(Arc::clone(&transport));
+                transport
             }
+        };
+
+        match self.connect_timeout {
+            Some(connect_timeout) => timeout(connect_timeout, transport.connect()).await.map_err(|_| TapError::Timeout)??,
+            None => transport.connect().await?,
+        }
+        self.event_manager.lock().await.set_transport(Arc::clone(&transport));
+        self.running = true;
 
-            for peripheral in peripherals.iter() {
-                let properties = peripheral.properties().await?;
-                let is_connected = peripheral.is_connected().await?;
-                let local_name = properties
-                    .unwrap()
-                    .local_name
-                    .unwrap_or(String::from(""));
+        if let Some(path) = self.record_to.take() {
+            self.start_recording(path).await?;
+        }
 
-                if is_connected && local_name.starts_with("Tap") {
-                    let is_connected = peripheral.is_connected().await?;
-                    let address = peripheral.address();
-                    println!("Now connected ({:?}) to peripheral {:?} ({:?})", is_connected, &local_name, address);
+        let info = self.device_info().await.unwrap_or_default();
+        self.model = detect_model(transport.as_ref(), &info).await;
+        self.firmware_version = info.firmware_revision.as_deref().and_then(FirmwareVersion::parse);
 
-                    return Ok(address)
-                }
-            }
+        self.set_initial_state().await?;
+        self.start_reconnect_watcher();
+        Ok(())
+    }
+
+    // `Unknown` until the client is connected.
+    pub fn model(&self) -> TapModel {
+        self.model
+    }
+
+    pub fn capabilities(&self) -> Capabilities {
+        self.model.capabilities()
+    }
+
+    // `None` until the client is connected, or when the revision can't be parsed.
+    pub fn firmware_version(&self) -> Option<FirmwareVersion> {
+        self.firmware_version
+    }
+
+    // Devices with an unknown firmware version are not refused.
+    fn require_firmware(&self, feature: FirmwareFeature) -> Result<(), TapError> {
+        match (self.firmware_version, self.min_firmware_versions.get(&feature)) {
+            (Some(current), Some(&required)) if current < required => Err(TapError::FirmwareTooOld { feature, required, current }),
+            _ => Ok(()),
         }
+    }
 
-        return Err("No Tap device was found. Make sure the device is connected and its human readable name starts with Tap.".into());
+    fn require(&self, supported: bool, feature: &'static str) -> Result<(), TapError> {
+        if supported {
+            Ok(())
+        } else {
+            Err(TapError::Unsupported { feature, model: self.model })
+        }
     }
 
-    async fn find_peripheral(&self) -> Option<btleplug::platform::Peripheral> {
-        let adapters = self.bluetooth_manager.adapters().await.unwrap();
-        let adapter = adapters.into_iter().next().unwrap();
+    pub fn set_reconnect_policy(&mut self, policy: Option<ReconnectPolicy>) {
+        self.reconnect_policy = policy;
+        self.start_reconnect_watcher();
+    }
 
-        let peripherals = adapter.peripherals().await.unwrap();
-        for peripheral in peripherals {
-            if peripheral.address() == self.address.unwrap() {
-                return Some(peripheral)
-            }
+    fn start_reconnect_watcher(&mut self) {
+        if let Some(task) = self.reconnect_task.take() {
+            task.abort();
         }
 
-        return None;
+        if let (true, Some(policy), Some(transport)) = (self.running, &self.reconnect_policy, &self.transport) {
+            self.reconnect_task = Some(tokio::spawn(watch_connection(
+                Arc::clone(transport),
+                Arc::clone(&self.event_manager),
+                Arc::clone(&self.state),
+                Arc::clone(&self.auto_refresh),
+                policy.clone(),
+            )));
+        }
     }
 
-    pub async fn run (&mut self) {
-        let peripheral = self.find_peripheral().await.unwrap();
-        peripheral.connect().await.unwrap();
-        peripheral.discover_services().await.unwrap();
+    // `None` turns the auto-refresh off, intervals below `MIN_AUTO_REFRESH_INTERVAL` are raised to it.
+    pub fn set_auto_refresh_interval(&mut self, interval: Option<Duration>) {
+        let mut auto_refresh = self.auto_refresh.lock().unwrap();
+        auto_refresh.interval = clamp_auto_refresh_interval(interval);
+        match (self.running, &self.transport) {
+            (true, Some(transport)) => auto_refresh.start(Arc::clone(transport), Arc::clone(&self.state)),
+            _ => auto_refresh.stop(),
+        }
+    }
+
+    pub fn auto_refresh_interval(&self) -> Option<Duration> {
+        self.auto_refresh.lock().unwrap().interval
+    }
 
-        let peripheral_arc = Arc::new(Mutex::new(peripheral));
-        self.peripheral = Some(Arc::clone(&peripheral_arc));
-        self.event_manager.lock().await.set_peripheral(Arc::clone(&peripheral_arc));
+    // Records every notification and command of the session to a JSONL file,
+    // replacing the current recording.
+    pub async fn start_recording(&mut self, path: impl AsRef<Path>) -> Result<(), TapError> {
+        let device = self.device_id().ok_or(TapError::DeviceNotFound)?;
+        let name = match &self.transport {
+            Some(transport) => transport.name().await,
+            None => None,
+        };
+        let recorder = Arc::new(SessionRecorder::create(path.as_ref(), device, name)?);
+        self.event_manager.lock().await.set_recorder(Some(Arc::clone(&recorder)));
+        self.state.lock().unwrap().recorder = Some(recorder);
+        Ok(())
+    }
 
-        self.set_initial_state().await;
+    pub async fn stop_recording(&mut self) {
+        self.event_manager.lock().await.set_recorder(None);
+        self.state.lock().unwrap().recorder = None;
     }
 
-    async fn set_initial_state(&mut self) {
-        println!("Setting initial state: Text mode and Auto type");
-        self.set_input_mode(InputMode::Text, None).await;
-        self.set_input_type(InputType::Auto).await;
+    pub fn is_recording(&self) -> bool {
+        self.state.lock().unwrap().recorder.is_some()
     }
 
-    async fn write_to_characteristic(&self, characteristic_uuid: Uuid, data: &[u8]) -> Result<(), Box<dyn Error>> {
-        if let Some(peripheral_arc) = &self.peripheral {
-            let characteristics = {
-                let peripheral = peripheral_arc.lock().await;
-                peripheral.characteristics().clone()
-            };
-            
-            for characteristic in &characteristics {
-                if characteristic.uuid == characteristic_uuid {
-                    let peripheral = peripheral_arc.lock().await;
-                    peripheral.write(characteristic, data, btleplug::api::WriteType::WithResponse).await?;
-                    return Ok(());
-                }
+    pub async fn replay(&self) -> Result<(), TapError> {
+        match &self.replay {
+            Some(replay) => replay.play().await,
+            None => Err(TapError::Protocol(String::from("The client was not created from a recording"))),
+        }
+    }
+
+    async fn set_initial_state(&mut self) -> Result<(), TapError> {
+        if let Some(mode) = self.initial_mode {
+            self.set_input_mode(mode, None).await?;
+        }
+        if let Some(input_type) = self.initial_input_type {
+            match self.set_input_type(input_type).await {
+                // Only Spatial Control devices with recent firmware select an input type.
+                Err(TapError::Unsupported { .. } | TapError::FirmwareTooOld { .. }) => {}
+                result => result?,
             }
-            Err(format!("Characteristic {:?} not found", characteristic_uuid).into())
-        } else {
-            Err("No peripheral connected".into())
         }
+        Ok(())
     }
 
-    pub async fn register_air_gesture_events(&self, listener: fn(Uuid, AirGestures)) {
-        self.event_manager.lock().await.register_air_gesture_events(listener).await;
+    async fn read_characteristic(&self, characteristic: Characteristic) -> Result<Vec<u8>, TapError> {
+        let transport = self.transport.as_ref().ok_or(TapError::NotConnected)?;
+        match transport.find_characteristic(Uuid::from_u128(characteristic.as_uuid())) {
+            Some(gatt_characteristic) => transport.read(&gatt_characteristic).await,
+            None => Err(TapError::CharacteristicMissing(characteristic)),
+        }
     }
-    pub async fn register_tap_events(&self, listener: fn(Uuid, u8)) {
-        self.event_manager.lock().await.register_tap_events(listener).await;
+
+    // `None` when the device does not have the characteristic.
+    async fn read_string(&self, characteristic: Characteristic) -> Result<Option<String>, TapError> {
+        match self.read_characteristic(characteristic).await {
+            Ok(value) => Ok(Some(String::from_utf8_lossy(&value).trim_end_matches('\0').trim().to_string())),
+            Err(TapError::CharacteristicMissing(_)) => Ok(None),
+            Err(e) => Err(e),
+        }
     }
-    pub async fn register_raw_data_events(&self, listener: fn(Uuid, Vec<u8>)) {
-        self.event_manager.lock().await.register_raw_data_events(listener).await;
+
+    // Battery level in percent.
+    pub async fn battery_level(&self) -> Result<u8, TapError> {
+        let value = self.read_characteristic(Characteristic::BatteryLevel).await?;
+        decode_battery(&value).ok_or_else(|| TapError::Protocol(String::from("Empty battery level")))
     }
-    pub async fn register_mouse_events(&self, listener: fn(Uuid, i16, i16, bool)) {
-        self.event_manager.lock().await.register_mouse_events(listener).await;
+
+    pub async fn device_info(&self) -> Result<DeviceInfo, TapError> {
+        Ok(DeviceInfo {
+            manufacturer: self.read_string(Characteristic::ManufacturerName).await?,
+            model_number: self.read_string(Characteristic::ModelNumber).await?,
+            serial_number: self.read_string(Characteristic::SerialNumber).await?,
+            hardware_revision: self.read_string(Characteristic::HardwareRevision).await?,
+            firmware_revision: self.read_string(Characteristic::FirmwareRevision).await?,
+            software_revision: self.read_string(Characteristic::SoftwareRevision).await?,
+        })
     }
-    pub async fn register_air_gesture_state_events(&self, listener: fn(Uuid, bool)) {
-        self.event_manager.lock().await.register_air_gesture_state_events(listener).await;
+
+    async fn write_to_characteristic(&self, characteristic: Characteristic, data: &[u8]) -> Result<(), TapError> {
+        let transport = self.transport.as_ref().ok_or(TapError::NotConnected)?;
+        write_command(transport.as_ref(), &self.state, characteristic, data).await
     }
 
-    pub async fn set_input_mode(&mut self, mode: InputMode, sensitivity: Option<Vec<u8>>) {
-        let tap_mode_uuid = Uuid::from_u128(crate::types::uuid::characteristics::Characteristic::TapMode.as_uuid());
-        
-        let command = mode.to_command(sensitivity);
+    pub async fn register_air_gesture_events(&self, listener: impl Fn(EventSource, AirGestures) + Send + Sync + 'static) -> Result<Subscription, TapError> {
+        self.require(self.capabilities().air_gestures, "Air gestures")?;
+        self.event_manager.lock().await.register_air_gesture_events(listener).await
+    }
+    pub async fn register_tap_events(&self, listener: impl Fn(EventSource, TapCode) + Send + Sync + 'static) -> Result<Subscription, TapError> {
+        self.event_manager.lock().await.register_tap_events(listener).await
+    }
+    pub async fn register_raw_data_events(&self, listener: impl Fn(EventSource, Vec<u8>) + Send + Sync + 'static) -> Result<Subscription, TapError> {
+        self.event_manager.lock().await.register_raw_data_events(listener).await
+    }
+    pub async fn register_raw_sensor_events(&self, listener: impl Fn(EventSource, RawSensorPacket) + Send + Sync + 'static) -> Result<Subscription, TapError> {
+        self.event_manager.lock().await.register_raw_sensor_events(listener).await
+    }
+    pub async fn register_mouse_events(&self, listener: impl Fn(EventSource, i16, i16, bool) + Send + Sync + 'static) -> Result<Subscription, TapError> {
+        self.event_manager.lock().await.register_mouse_events(listener).await
+    }
+    pub async fn register_air_gesture_state_events(&self, listener: impl Fn(EventSource, bool) + Send + Sync + 'static) -> Result<Subscription, TapError> {
+        self.require(self.capabilities().spatial_control, "Extended air gesture state")?;
+        self.require_firmware(FirmwareFeature::AirGestureState)?;
+        self.event_manager.lock().await.register_air_gesture_state_events(listener).await
+    }
+
+    pub async fn register_battery_events(&self, listener: impl Fn(EventSource, u8) + Send + Sync + 'static) -> Result<Subscription, TapError> {
+        self.event_manager.lock().await.register_battery_events(listener).await
+    }
+
+    pub async fn register_connection_events(&self, listener: impl Fn(EventSource) + Send + Sync + 'static) -> Result<Subscription, TapError> {
+        self.event_manager.lock().await.register_connection_events(listener).await
+    }
+    pub async fn register_disconnection_events(&self, listener: impl Fn(EventSource) + Send + Sync + 'static) -> Result<Subscription, TapError> {
+        self.event_manager.lock().await.register_disconnection_events(listener).await
+    }
+
+    // Air gesture state events are left out where `register_air_gesture_state_events` is refused.
+    pub async fn events(&self) -> Result<EventStream, TapError> {
+        let events = self.event_manager.lock().await.events().await?;
+        if self.air_gesture_state_supported() {
+            return Ok(events);
+        }
+        Ok(Box::pin(events.filter(|event| future::ready(!matches!(event, TapEvent::AirGestureState { .. })))))
+    }
+
+    fn air_gesture_state_supported(&self) -> bool {
+        self.capabilities().spatial_control && self.require_firmware(FirmwareFeature::AirGestureState).is_ok()
+    }
+
+    // Runs an async handler for every event, one event at a time,
+    // so a slow handler holds back the following events.
+    pub async fn register_event_handler<F, Fut>(&self, handler: F) -> Result<Subscription, TapError>
+    where
+        F: Fn(TapEvent) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        let mut events = self.events().await?;
+        let task = tokio::spawn(async move {
+            while let Some(event) = events.next().await {
+                handler(event).await;
+            }
+        });
+        Ok(self.event_manager.lock().await.track_task(task.abort_handle()))
+    }
+
+    pub async fn set_input_mode(&mut self, mode: InputMode, sensitivity: Option<Vec<u8>>) -> Result<(), TapError> {
+        let command = mode.to_command(sensitivity.clone());
         
         println!("Setting input mode to {:?} with command: {:?}", mode, command);
-        if let Err(e) = self.write_to_characteristic(tap_mode_uuid, &command).await {
-            eprintln!("Failed to set input mode: {:?}", e);
-        }
+        self.write_to_characteristic(Characteristic::TapMode, &command).await?;
 
-        self.input_mode = Some(mode);
+        {
+            let mut state = self.state.lock().unwrap();
+            if let InputMode::Raw = mode {
+                state.raw_sensor_config = RawSensorConfig::from_sensitivity(sensitivity.as_deref().unwrap_or_default());
+            }
+            state.input_mode = mode;
+            state.sensitivity = sensitivity;
+        }
         self.start_auto_refresh().await;
+        Ok(())
     }
 
-    pub async fn set_input_type(&mut self, input_type: InputType) {
-        let tap_mode_uuid = Uuid::from_u128(crate::types::uuid::characteristics::Characteristic::TapMode.as_uuid());
-        
+    pub async fn set_raw_mode(&mut self, config: RawSensorConfig) -> Result<(), TapError> {
+        self.set_input_mode(InputMode::Raw, Some(config.to_sensitivity())).await
+    }
+
+    pub fn raw_sensor_config(&self) -> RawSensorConfig {
+        self.state.lock().unwrap().raw_sensor_config
+    }
+
+    pub async fn set_input_type(&mut self, input_type: InputType) -> Result<(), TapError> {
+        self.require(self.capabilities().spatial_control, "Input type selection")?;
+        self.require_firmware(FirmwareFeature::InputType)?;
         let command = input_type.to_command();
         
         println!("Setting input type to {:?} with command: {:?}", input_type, command);
-        if let Err(e) = self.write_to_characteristic(tap_mode_uuid, &command).await {
-            eprintln!("Failed to set input type: {:?}", e);
-        }
+        self.write_to_characteristic(Characteristic::TapMode, &command).await?;
 
-        self.input_type = Some(input_type);
+        self.state.lock().unwrap().input_type = Some(input_type);
         self.start_auto_refresh().await;
+        Ok(())
     }
 
-    pub async fn set_mouse_mode(&self, mouse_mode: MouseModes) {
-        let tap_mode_uuid = Uuid::from_u128(crate::types::uuid::characteristics::Characteristic::TapMode.as_uuid());
-        
-        let command = vec![0x03, 0x0e, 0x00, mouse_mode.to_uid()];
+    pub async fn set_mouse_mode(&self, mouse_mode: MouseModes) -> Result<(), TapError> {
+        match mouse_mode {
+            MouseModes::AirMouse => self.require(self.capabilities().air_mouse, "Air mouse")?,
+            MouseModes::OpticalOne | MouseModes::OpticalTwo => self.require(self.capabilities().optical_mouse, "Optical mouse")?,
+            MouseModes::Stdby => {}
+        }
+        let command = mouse_mode.to_command();
         
         println!("Setting mouse mode to {:?} with command: {:?}", mouse_mode, command);
-        if let Err(e) = self.write_to_characteristic(tap_mode_uuid, &command).await {
-            eprintln!("Failed to set mouse mode: {:?}", e);
-        }
+        self.write_to_characteristic(Characteristic::TapMode, &command).await?;
+
+        self.state.lock().unwrap().mouse_mode = Some(mouse_mode);
+        Ok(())
     }
 
-    pub async fn send_vibration_sequence(&self, sequence: Vec<u16>) {
-        let ui_cmd_uuid = Uuid::from_u128(crate::types::uuid::characteristics::Characteristic::UiCmd.as_uuid());
-        
+    pub async fn send_vibration_sequence(&self, sequence: Vec<u16>) -> Result<(), TapError> {
         let mut command = vec![0x00, 0x02];
         for &duration in sequence.iter().take(18) {
-            let value = ((duration / 10) as u8).min(255);
+            let value = (duration / 10).min(255) as u8;
             command.push(value);
         }
         
         println!("Sending vibration sequence: {:?} -> command: {:?}", sequence, command);
-        if let Err(e) = self.write_to_characteristic(ui_cmd_uuid, &command).await {
-            eprintln!("Failed to send vibration sequence: {:?}", e);
-        }
+        self.write_to_characteristic(Characteristic::UiCmd, &command).await
     }
 
     async fn start_auto_refresh(&mut self) {
-        if self.auto_refresh_running {
+        let mut auto_refresh = self.auto_refresh.lock().unwrap();
+        if auto_refresh.is_running() {
+            return;
+        }
+
+        if let (true, Some(transport)) = (self.running, &self.transport) {
+            auto_refresh.start(Arc::clone(transport), Arc::clone(&self.state));
+        }
+    }
+}
+
+impl Drop for Client {
+    fn drop(&mut self) {
+        if let Some(task) = self.reconnect_task.take() {
+            task.abort();
+        }
+        self.auto_refresh.lock().unwrap().stop();
+    }
+}
+
+async fn detect_model(transport: &dyn TapTransport, info: &DeviceInfo) -> TapModel {
+    let name = transport.name().await;
+    let characteristics: Vec<Characteristic> = transport
+        .characteristics()
+        .iter()
+        .filter_map(|characteristic| Characteristic::from_uuid(characteristic.uuid).ok())
+        .collect();
+    TapModel::detect(name.as_deref(), info, &characteristics)
+}
+
+async fn write_command(transport: &dyn TapTransport, state: &StdMutex<DeviceState>, characteristic: Characteristic, data: &[u8]) -> Result<(), TapError> {
+    let gatt_characteristic = transport
+        .find_characteristic(Uuid::from_u128(characteristic.as_uuid()))
+        .ok_or(TapError::CharacteristicMissing(characteristic))?;
+    transport.write(&gatt_characteristic, data).await?;
+
+    let recorder = state.lock().unwrap().recorder.clone();
+    if let Some(recorder) = recorder {
+        recorder.record_command(characteristic, data);
+    }
+    Ok(())
+}
+
+// Waits for the device to drop its link, then reconnects following the policy,
+// subscribes every listener again and restores the last input mode and type.
+async fn watch_connection(
+    transport: Arc<dyn TapTransport>,
+    event_manager: Arc<Mutex<EventManager>>,
+    state: Arc<StdMutex<DeviceState>>,
+    auto_refresh: Arc<StdMutex<AutoRefresh>>,
+    policy: ReconnectPolicy,
+) {
+    let mut connection_events = match transport.connection_events().await {
+        Ok(connection_events) => connection_events,
+        Err(e) => {
+            eprintln!("Failed to watch Tap device connection: {:?}", e);
+            return;
+        }
+    };
+
+    while let Some(connection_state) = connection_events.next().await {
+        if connection_state != ConnectionState::Disconnected || transport.is_connected().await.unwrap_or(false) {
+            continue;
+        }
+
+        let mut attempt = 0;
+        loop {
+            if policy.max_attempts.is_some_and(|max_attempts| attempt >= max_attempts) {
+                eprintln!("Giving up reconnecting to Tap device {} after {} attempts", transport.address(), attempt);
+                return;
+            }
+            sleep(policy.delay(attempt)).await;
+            attempt += 1;
+
+            match transport.reconnect().await {
+                Ok(()) => break,
+                Err(e) => eprintln!("Reconnect attempt {} failed: {:?}", attempt, e),
+            }
+        }
+
+        if let Err(e) = event_manager.lock().await.resubscribe().await {
+            eprintln!("Failed to restore subscriptions: {:?}", e);
+        }
+
+        let commands = state.lock().unwrap().commands();
+        for (characteristic, command) in commands {
+            if let Err(e) = write_command(transport.as_ref(), &state, characteristic, &command).await {
+                eprintln!("Failed to restore {:?} command {:?}: {:?}", characteristic, command, e);
+            }
+        }
+        auto_refresh.lock().unwrap().start(Arc::clone(&transport), Arc::clone(&state));
+    }
+}
+
+// Rewrites the current input mode and input type until the device disconnects.
+async fn refresh_input_mode(transport: Arc<dyn TapTransport>, state: Arc<StdMutex<DeviceState>>, interval: Duration) {
+    let mut connection_events = match transport.connection_events().await {
+        Ok(connection_events) => connection_events,
+        Err(e) => {
+            eprintln!("Failed to watch Tap device connection: {:?}", e);
             return;
         }
+    };
 
-        self.auto_refresh_running = true;
-        println!("Auto-refresh mechanism started (simplified version)");
+    let mut ticks = tokio::time::interval_at(tokio::time::Instant::now() + interval, interval);
+    loop {
+        tokio::select! {
+            _ = ticks.tick() => {
+                let commands = {
+                    let state = state.lock().unwrap();
+                    let mut commands = vec![state.input_mode.to_command(state.sensitivity.clone())];
+                    commands.extend(state.input_type.map(|input_type| input_type.to_command()));
+                    commands
+                };
+                for command in commands {
+                    if let Err(e) = write_command(transport.as_ref(), &state, Characteristic::TapMode, &command).await {
+                        eprintln!("Failed to refresh input mode: {:?}", e);
+                    }
+                }
+            }
+            connection_state = connection_events.next() => {
+                if matches!(connection_state, None | Some(ConnectionState::Disconnected)) {
+                    return;
+                }
+            }
+        }
     }
 }
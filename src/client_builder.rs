@@ -0,0 +1,177 @@
+use btleplug::api::BDAddr;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::client::{clamp_auto_refresh_interval, DEFAULT_AUTO_REFRESH_INTERVAL};
+use crate::discovery::{DeviceSelector, DiscoveryOptions};
+use crate::error::TapError;
+use crate::reconnect::ReconnectPolicy;
+use crate::transport::TapTransport;
+use crate::types::firmware::{FirmwareFeature, FirmwareVersion};
+use crate::types::input_type::{InputMode, InputType};
+use crate::Client;
+
+// [markdown]
+// `ClientBuilder` configures a `Client` before it connects:
+// ```rust
+// let tap_client = ClientBuilder::new()
+//     .name("Tap_Kiosk_1")
+//     .scan_timeout(Duration::from_secs(5))
+//     .connect_timeout(Duration::from_secs(10))
+//     .initial_mode(Some(InputMode::Controller))
+//     .reconnect_policy(Some(ReconnectPolicy::default()))
+//     .connect()
+//     .await?;
+// ```
+// Without any settings it behaves like `Client::new(None)`.
+#[derive(Debug, Clone)]
+pub struct ClientBuilder {
+    pub(crate) device: DeviceSelector,
+    pub(crate) transport: Option<Arc<dyn TapTransport>>,
+    pub(crate) discovery: DiscoveryOptions,
+    pub(crate) connect_timeout: Option<Duration>,
+    pub(crate) initial_mode: Option<InputMode>,
+    pub(crate) initial_input_type: Option<InputType>,
+    pub(crate) auto_refresh_interval: Option<Duration>,
+    pub(crate) reconnect_policy: Option<ReconnectPolicy>,
+    pub(crate) record_to: Option<PathBuf>,
+    pub(crate) min_firmware_versions: HashMap<FirmwareFeature, FirmwareVersion>,
+}
+
+impl Default for ClientBuilder {
+    fn default() -> Self {
+        ClientBuilder {
+            device: DeviceSelector::FirstFound,
+            transport: None,
+            discovery: DiscoveryOptions::default(),
+            connect_timeout: None,
+            initial_mode: Some(InputMode::Text),
+            initial_input_type: Some(InputType::Auto),
+            auto_refresh_interval: Some(DEFAULT_AUTO_REFRESH_INTERVAL),
+            reconnect_policy: None,
+            record_to: None,
+            min_firmware_versions: HashMap::new(),
+        }
+    }
+}
+
+impl ClientBuilder {
+    pub fn new() -> Self {
+        ClientBuilder::default()
+    }
+
+    // Index into the Bluetooth adapters of the system.
+    pub fn adapter(mut self, index: usize) -> Self {
+        self.discovery.adapter = Some(index);
+        self
+    }
+
+    pub fn device(mut self, selector: DeviceSelector) -> Self {
+        self.device = selector;
+        self
+    }
+
+    pub fn address(self, address: BDAddr) -> Self {
+        self.device(DeviceSelector::Address(address))
+    }
+
+    pub fn name(self, name: &str) -> Self {
+        self.device(DeviceSelector::Name(name.to_string()))
+    }
+
+    // Talks to the device through this transport instead of Bluetooth,
+    // e.g. a `SimulatedTap`. The device selector and scan settings are ignored.
+    pub fn transport(mut self, transport: Arc<dyn TapTransport>) -> Self {
+        self.transport = Some(transport);
+        self
+    }
+
+    pub fn scan_timeout(mut self, timeout: Duration) -> Self {
+        self.discovery.scan_timeout = timeout;
+        self
+    }
+
+    // Only used with `DeviceSelector::FirstFound`.
+    pub fn name_prefixes(mut self, prefixes: Vec<String>) -> Self {
+        self.discovery.name_prefixes = prefixes;
+        self
+    }
+
+    pub fn connect_timeout(mut self, timeout: Duration) -> Self {
+        self.connect_timeout = Some(timeout);
+        self
+    }
+
+    // Sent right after connecting, `None` leaves the device as it is.
+    pub fn initial_mode(mut self, mode: Option<InputMode>) -> Self {
+        self.initial_mode = mode;
+        self
+    }
+
+    pub fn initial_input_type(mut self, input_type: Option<InputType>) -> Self {
+        self.initial_input_type = input_type;
+        self
+    }
+
+    // Intervals below `MIN_AUTO_REFRESH_INTERVAL` are raised to it.
+    pub fn auto_refresh_interval(mut self, interval: Option<Duration>) -> Self {
+        self.auto_refresh_interval = clamp_auto_refresh_interval(interval);
+        self
+    }
+
+    pub fn reconnect_policy(mut self, policy: Option<ReconnectPolicy>) -> Self {
+        self.reconnect_policy = policy;
+        self
+    }
+
+    // Records the session from the moment the client connects, see `Client::start_recording`.
+    pub fn record_to(mut self, path: impl Into<PathBuf>) -> Self {
+        self.record_to = Some(path.into());
+        self
+    }
+
+    // Refuses the feature with `TapError::FirmwareTooOld` on older firmware.
+    pub fn min_firmware_version(mut self, feature: FirmwareFeature, version: FirmwareVersion) -> Self {
+        self.min_firmware_versions.insert(feature, version);
+        self
+    }
+
+    // Finds the device, `Client::run` connects to it.
+    pub async fn build(self) -> Result<Client, TapError> {
+        Client::from_builder(self).await
+    }
+
+    pub async fn connect(self) -> Result<Client, TapError> {
+        let mut client = self.build().await?;
+        client.run().await?;
+        Ok(client)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::MIN_AUTO_REFRESH_INTERVAL;
+    use crate::transport::SimulatedTap;
+
+    #[tokio::test]
+    async fn zero_auto_refresh_interval_is_clamped() {
+        let simulated_tap = Arc::new(SimulatedTap::new());
+        let mut tap_client = ClientBuilder::new()
+            .transport(simulated_tap.clone())
+            .auto_refresh_interval(Some(Duration::ZERO))
+            .connect()
+            .await
+            .unwrap();
+        assert_eq!(tap_client.auto_refresh_interval(), Some(MIN_AUTO_REFRESH_INTERVAL));
+
+        tap_client.set_auto_refresh_interval(Some(Duration::ZERO));
+        assert_eq!(tap_client.auto_refresh_interval(), Some(MIN_AUTO_REFRESH_INTERVAL));
+
+        simulated_tap.clear_written_commands();
+        tokio::time::sleep(MIN_AUTO_REFRESH_INTERVAL * 3).await;
+        assert!(!simulated_tap.written_commands().is_empty());
+    }
+}
@@ -0,0 +1,188 @@
+use btleplug::api::{BDAddr, Central, Manager as _, Peripheral, ScanFilter};
+use btleplug::platform::{Adapter, Manager, Peripheral as PlatformPeripheral};
+use std::time::Duration;
+use tokio::time::sleep;
+use uuid::Uuid;
+
+use crate::error::TapError;
+use crate::types::model::TapModel;
+use crate::types::uuid::services::Service;
+
+// [markdown]
+// `DiscoveryOptions` controls `discover`. Only devices whose name starts
+// with one of `name_prefixes` are listed (any name when empty), and with
+// `services` set, only devices advertising one of these services. Devices
+// that are already connected often stop advertising, so they pass the
+// service filter on their name alone. `adapter` is an index into the
+// adapters of the system, every adapter is scanned when it is `None`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DiscoveryOptions {
+    pub scan_timeout: Duration,
+    pub name_prefixes: Vec<String>,
+    pub services: Vec<Service>,
+    pub adapter: Option<usize>,
+}
+
+impl Default for DiscoveryOptions {
+    fn default() -> Self {
+        DiscoveryOptions {
+            scan_timeout: Duration::from_secs(2),
+            name_prefixes: vec![String::from("Tap")],
+            services: Vec::new(),
+            adapter: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DiscoveredTap {
+    pub address: BDAddr,
+    pub name: Option<String>,
+    pub rssi: Option<i16>,
+    pub connected: bool,
+    pub model: TapModel,
+}
+
+// [markdown]
+// `DeviceSelector` picks the Tap device a `Client` connects to. The device
+// only has to be advertising, it does not need to be paired or connected
+// in the OS Bluetooth settings first. `FirstFound` prefers devices that are
+// already connected, then the strongest signal.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub enum DeviceSelector {
+    Address(BDAddr),
+    Name(String),
+    #[default]
+    FirstFound,
+}
+
+impl DeviceSelector {
+    pub fn matches(&self, tap: &DiscoveredTap) -> bool {
+        match self {
+            DeviceSelector::Address(address) => tap.address == *address,
+            DeviceSelector::Name(name) => tap.name.as_deref() == Some(name.as_str()),
+            DeviceSelector::FirstFound => true,
+        }
+    }
+}
+
+// Scans until the selected device shows up. Selecting by address or name
+// ignores the name prefixes, so renamed devices can be found too.
+pub(crate) async fn select_with(bluetooth_manager: &Manager, selector: &DeviceSelector, options: &DiscoveryOptions) -> Result<DiscoveredTap, TapError> {
+    let mut options = options.clone();
+    if *selector != DeviceSelector::FirstFound {
+        options.name_prefixes.clear();
+    }
+    discover_with(bluetooth_manager, &options)
+        .await?
+        .into_iter()
+        .find(|tap| selector.matches(tap))
+        .ok_or(TapError::DeviceNotFound)
+}
+
+// Scans every Bluetooth adapter and lists the Tap devices nearby,
+// connected devices first, then by signal strength.
+pub async fn discover(options: &DiscoveryOptions) -> Result<Vec<DiscoveredTap>, TapError> {
+    let bluetooth_manager = Manager::new().await?;
+    discover_with(&bluetooth_manager, options).await
+}
+
+pub(crate) async fn discover_with(bluetooth_manager: &Manager, options: &DiscoveryOptions) -> Result<Vec<DiscoveredTap>, TapError> {
+    let adapters = adapters(bluetooth_manager, options).await?;
+    let services: Vec<Uuid> = options
+        .services
+        .iter()
+        .map(|service| Uuid::from_u128(service.as_uuid()))
+        .collect();
+    let mut scanning = Vec::new();
+    let mut started = Ok(());
+    for adapter in &adapters {
+        if let Err(e) = adapter.start_scan(ScanFilter { services: services.clone() }).await {
+            started = Err(e);
+            break;
+        }
+        scanning.push(adapter);
+    }
+    let found = match started {
+        Ok(()) => {
+            sleep(options.scan_timeout).await;
+            list_taps(&adapters, options, &services).await
+        }
+        Err(e) => Err(e.into()),
+    };
+
+    // Every adapter stops scanning, whatever the outcome of the lookup.
+    let mut stopped = Ok(());
+    for adapter in scanning {
+        stopped = stopped.and(adapter.stop_scan().await);
+    }
+    let mut discovered = found?;
+    stopped?;
+
+    discovered.sort_by_key(|tap| (!tap.connected, std::cmp::Reverse(tap.rssi)));
+    Ok(discovered)
+}
+
+// The Tap devices the adapters have seen, each listed once.
+async fn list_taps(adapters: &[Adapter], options: &DiscoveryOptions, services: &[Uuid]) -> Result<Vec<DiscoveredTap>, TapError> {
+    let mut discovered = Vec::new();
+    for adapter in adapters {
+        for peripheral in adapter.peripherals().await? {
+            let Some(properties) = peripheral.properties().await? else {
+                continue;
+            };
+            let name = properties.local_name;
+            let name_matches = options.name_prefixes.is_empty()
+                || name
+                    .as_deref()
+                    .is_some_and(|name| options.name_prefixes.iter().any(|prefix| name.starts_with(prefix.as_str())));
+            if !name_matches {
+                continue;
+            }
+
+            let connected = peripheral.is_connected().await?;
+            let advertises_service = properties.services.iter().any(|uuid| services.contains(uuid));
+            if !services.is_empty() && !advertises_service && !connected {
+                continue;
+            }
+
+            if discovered.iter().any(|tap: &DiscoveredTap| tap.address == peripheral.address()) {
+                continue;
+            }
+            discovered.push(DiscoveredTap {
+                address: peripheral.address(),
+                model: name.as_deref().map(TapModel::from_name).unwrap_or_default(),
+                name,
+                rssi: properties.rssi,
+                connected,
+            });
+        }
+    }
+    Ok(discovered)
+}
+
+pub(crate) async fn adapters(bluetooth_manager: &Manager, options: &DiscoveryOptions) -> Result<Vec<Adapter>, TapError> {
+    let mut adapters = bluetooth_manager.adapters().await?;
+    if let Some(index) = options.adapter {
+        adapters = adapters.into_iter().nth(index).into_iter().collect();
+    }
+    if adapters.is_empty() {
+        return Err(TapError::NoAdapter);
+    }
+    Ok(adapters)
+}
+
+// Looks the device up among the peripherals the adapters already know about.
+pub(crate) async fn find_peripheral(bluetooth_manager: &Manager, address: BDAddr, options: &DiscoveryOptions) -> Result<Option<(Adapter, PlatformPeripheral)>, TapError> {
+    for adapter in adapters(bluetooth_manager, options).await? {
+        if let Some(peripheral) = adapter
+            .peripherals()
+            .await?
+            .into_iter()
+            .find(|peripheral| peripheral.address() == address)
+        {
+            return Ok(Some((adapter, peripheral)));
+        }
+    }
+    Ok(None)
+}
@@ -0,0 +1,63 @@
+use std::error::Error;
+use std::fmt;
+
+use crate::types::firmware::{FirmwareFeature, FirmwareVersion};
+use crate::types::model::TapModel;
+use crate::types::uuid::characteristics::Characteristic;
+
+// [markdown]
+// `TapError` is returned by every fallible call of the SDK,
+// so applications can recover from a missing adapter or a lost
+// device instead of crashing.
+#[derive(Debug)]
+pub enum TapError {
+    NoAdapter,
+    DeviceNotFound,
+    CharacteristicMissing(Characteristic),
+    NotConnected,
+    Ble(btleplug::Error),
+    Timeout,
+    Protocol(String),
+    Unsupported { feature: &'static str, model: TapModel },
+    FirmwareTooOld { feature: FirmwareFeature, required: FirmwareVersion, current: FirmwareVersion },
+    Io(std::io::Error),
+}
+
+impl fmt::Display for TapError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TapError::NoAdapter => write!(f, "No Bluetooth adapter found"),
+            TapError::DeviceNotFound => write!(f, "No Tap device was found. Make sure the device is connected and its human readable name starts with Tap."),
+            TapError::CharacteristicMissing(characteristic) => write!(f, "Characteristic {:?} not found on the Tap device", characteristic),
+            TapError::NotConnected => write!(f, "Tap device is not connected"),
+            TapError::Ble(error) => write!(f, "Bluetooth error: {}", error),
+            TapError::Timeout => write!(f, "Timed out waiting for the Tap device"),
+            TapError::Protocol(message) => write!(f, "Protocol error: {}", message),
+            TapError::Unsupported { feature, model } => write!(f, "{} not supported on {}", feature, model),
+            TapError::FirmwareTooOld { feature, required, current } => write!(f, "{} requires firmware {} or newer, the Tap device runs {}", feature, required, current),
+            TapError::Io(error) => write!(f, "I/O error: {}", error),
+        }
+    }
+}
+
+impl Error for TapError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            TapError::Ble(error) => Some(error),
+            TapError::Io(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<btleplug::Error> for TapError {
+    fn from(error: btleplug::Error) -> Self {
+        TapError::Ble(error)
+    }
+}
+
+impl From<std::io::Error> for TapError {
+    fn from(error: std::io::Error) -> Self {
+        TapError::Io(error)
+    }
+}
@@ -1,133 +1,607 @@
-use btleplug::platform::Peripheral as PlatformPeripheral;
-use uuid::Uuid;
+use crate::error::TapError;
+use crate::recorder::SessionRecorder;
+use crate::transport::{NotificationStream, TapTransport};
 use crate::types::air_gestures::AirGestures;
+use crate::types::device::{ConnectionState, DeviceId, EventSource};
+use crate::types::event::{decode_air_gesture, decode_air_gesture_state, decode_battery, decode_mouse, decode_tap, EventStream, TapEvent};
+use crate::types::raw_sensors::RawSensorPacket;
+use crate::types::tap_code::TapCode;
 use crate::types::uuid::characteristics::Characteristic;
-use std::sync::Arc;
-use tokio::sync::Mutex;
+use std::collections::HashSet;
+use std::fmt;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex as StdMutex, Weak};
+use std::task::{Context, Poll};
+use btleplug::api::CharPropFlags;
+use futures::stream::Stream;
 use futures::StreamExt;
-use btleplug::api::Peripheral;
+use tokio::sync::mpsc::{self, Receiver, Sender};
+use tokio::sync::Mutex;
+use tokio::task::AbortHandle;
+use uuid::Uuid;
+
+// How many events `events()` buffers before further events are dropped.
+pub const EVENT_STREAM_CAPACITY: usize = 64;
+
+type NotificationCallback = Arc<dyn Fn(EventSource, &[u8]) + Send + Sync>;
+
+#[derive(Clone)]
+enum Listener {
+    Callback(NotificationCallback),
+    Stream(Arc<StreamSender>),
+}
+
+// The sending side of an `events()` stream. The dispatcher never waits for
+// the consumer: events that don't fit are dropped and counted, and the count
+// is delivered as `TapEvent::Lagged` as soon as the stream has room again.
+struct StreamSender {
+    sender: Sender<TapEvent>,
+    missed: AtomicU64,
+}
+
+impl StreamSender {
+    fn send(&self, event: TapEvent) {
+        let missed = self.missed.load(Ordering::Relaxed);
+        if missed > 0 {
+            let lagged = TapEvent::Lagged { source: event.source().clone(), missed };
+            if self.sender.try_send(lagged).is_err() {
+                self.missed.fetch_add(1, Ordering::Relaxed);
+                return;
+            }
+            self.missed.fetch_sub(missed, Ordering::Relaxed);
+        }
+        // A closed stream is removed with its subscription.
+        if let Err(mpsc::error::TrySendError::Full(_)) = self.sender.try_send(event) {
+            self.missed.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    fn is_closed(&self) -> bool {
+        self.sender.is_closed()
+    }
+}
+
+#[derive(Clone)]
+enum Target {
+    // Served by the notification dispatcher.
+    Notification { characteristic: Characteristic, listener: Listener },
+    // Runs on its own, e.g. connection state listeners.
+    Task(AbortHandle),
+}
+
+#[derive(Clone)]
+struct Registration {
+    id: u64,
+    target: Target,
+}
+
+impl fmt::Debug for Registration {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let characteristic = match &self.target {
+            Target::Notification { characteristic, .. } => Some(characteristic),
+            Target::Task(_) => None,
+        };
+        f.debug_struct("Registration")
+            .field("id", &self.id)
+            .field("characteristic", &characteristic)
+            .finish()
+    }
+}
+
+// Shared with every `Subscription`, so dropping one removes its listener
+// without going through the `EventManager`.
+#[derive(Debug, Default)]
+struct Registry {
+    transport: Option<Arc<dyn TapTransport>>,
+    next_id: u64,
+    registrations: Vec<Registration>,
+    // Characteristics with GATT notifications enabled on the device.
+    subscribed: HashSet<Characteristic>,
+    // Held while notifications are turned on or off on the device, so an
+    // unsubscribe still pending from a dropped `Subscription` can't undo a newer subscribe.
+    gatt: Arc<Mutex<()>>,
+    dispatcher: Option<AbortHandle>,
+    recorder: Option<Arc<SessionRecorder>>,
+}
+
+impl Registry {
+    fn add(&mut self, target: Target) -> u64 {
+        let id = self.next_id;
+        self.next_id += 1;
+        self.registrations.push(Registration { id, target });
+        id
+    }
+
+    fn listeners(&self, characteristic: Characteristic) -> Vec<Listener> {
+        self.registrations
+            .iter()
+            .filter_map(|registration| match &registration.target {
+                Target::Notification { characteristic: listened, listener } if *listened == characteristic => Some(listener.clone()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    fn listened_characteristics(&self) -> HashSet<Characteristic> {
+        self.registrations
+            .iter()
+            .filter_map(|registration| match &registration.target {
+                Target::Notification { characteristic, .. } => Some(*characteristic),
+                Target::Task(_) => None,
+            })
+            .collect()
+    }
+
+    // Returns the characteristics nobody listens to anymore.
+    fn remove(&mut self, ids: &[u64]) -> Vec<Characteristic> {
+        self.registrations.retain(|registration| {
+            if !ids.contains(&registration.id) {
+                return true;
+            }
+            if let Target::Task(task) = &registration.target {
+                task.abort();
+            }
+            false
+        });
+
+        let listened = self.listened_characteristics();
+        let unused: Vec<Characteristic> = self.subscribed.difference(&listened).copied().collect();
+        self.subscribed.retain(|characteristic| listened.contains(characteristic));
+        if listened.is_empty() {
+            self.stop_dispatcher();
+        }
+        unused
+    }
+
+    fn stop_dispatcher(&mut self) {
+        if let Some(dispatcher) = self.dispatcher.take() {
+            dispatcher.abort();
+        }
+    }
+}
+
+// [markdown]
+// `Subscription` is returned by every `register_*` call. The listener is
+// removed when the subscription is dropped or cancelled, and once the last
+// listener of a characteristic is gone, its notifications are unsubscribed
+// on the device.
+#[must_use = "the listener is removed as soon as the subscription is dropped"]
+#[derive(Debug)]
+pub struct Subscription {
+    registry: Weak<StdMutex<Registry>>,
+    ids: Vec<u64>,
+}
+
+impl Subscription {
+    pub fn cancel(self) {}
+
+    fn unregister(&mut self) {
+        let Some(registry) = self.registry.upgrade() else {
+            return;
+        };
+        let (transport, gatt, unused) = {
+            let mut registry = registry.lock().unwrap();
+            (registry.transport.clone(), Arc::clone(&registry.gatt), registry.remove(&self.ids))
+        };
+        let registry = Arc::downgrade(&registry);
+        self.ids.clear();
+
+        let (Some(transport), Ok(runtime)) = (transport, tokio::runtime::Handle::try_current()) else {
+            return;
+        };
+        if unused.is_empty() {
+            return;
+        }
+        runtime.spawn(async move {
+            let _gatt = gatt.lock().await;
+            for characteristic in unused {
+                // Listened to again since the subscription was dropped.
+                let listened = registry.upgrade().is_some_and(|registry| registry.lock().unwrap().subscribed.contains(&characteristic));
+                if listened {
+                    continue;
+                }
+                if let Some(gatt_characteristic) = transport.find_characteristic(Uuid::from_u128(characteristic.as_uuid())) {
+                    if let Err(e) = transport.unsubscribe(&gatt_characteristic).await {
+                        eprintln!("Failed to unsubscribe from {:?}: {:?}", characteristic, e);
+                    }
+                }
+            }
+        });
+    }
+}
 
-#[derive(Clone, Debug)]
+impl Drop for Subscription {
+    fn drop(&mut self) {
+        self.unregister();
+    }
+}
+
+// Keeps the listeners of an event stream alive as long as the stream.
+struct SubscribedStream {
+    receiver: Receiver<TapEvent>,
+    _subscription: Subscription,
+}
+
+impl Stream for SubscribedStream {
+    type Item = TapEvent;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<TapEvent>> {
+        self.receiver.poll_recv(cx)
+    }
+}
+
+// [markdown]
+// `EventManager` keeps the listeners of one device. A single dispatcher task
+// reads the notification stream of the peripheral and routes every packet
+// to the listeners of its characteristic, and every characteristic is
+// subscribed on the device only once, however many listeners it has.
+#[derive(Clone, Debug, Default)]
 pub struct EventManager {
-    peripheral: Option<Arc<Mutex<PlatformPeripheral>>>,
+    registry: Arc<StdMutex<Registry>>,
 }
 
 impl EventManager {
     pub fn new() -> Self {
-        EventManager { peripheral: None }
+        EventManager::default()
     }
 
-    pub fn set_peripheral(&mut self, peripheral: Arc<Mutex<PlatformPeripheral>>) {
-        self.peripheral = Some(peripheral);
+    pub fn set_transport(&mut self, transport: Arc<dyn TapTransport>) {
+        self.registry.lock().unwrap().transport = Some(transport);
     }
 
-    async fn subscribe_to_characteristic<F>(&self, characteristic_uuid: Uuid, callback: F) -> Result<(), Box<dyn std::error::Error>>
+    // Every notification the dispatcher receives is written to the recorder.
+    pub fn set_recorder(&self, recorder: Option<Arc<SessionRecorder>>) {
+        self.registry.lock().unwrap().recorder = recorder;
+    }
+
+    fn transport(&self) -> Result<Arc<dyn TapTransport>, TapError> {
+        self.registry.lock().unwrap().transport.clone().ok_or(TapError::NotConnected)
+    }
+
+    fn subscription(&self, ids: Vec<u64>) -> Subscription {
+        Subscription {
+            registry: Arc::downgrade(&self.registry),
+            ids,
+        }
+    }
+
+    // Tracks a task that is not tied to a characteristic, so it can be stopped with a `Subscription`.
+    pub(crate) fn track_task(&self, task: AbortHandle) -> Subscription {
+        let id = self.registry.lock().unwrap().add(Target::Task(task));
+        self.subscription(vec![id])
+    }
+
+    async fn subscribe_to_characteristic<F>(&self, characteristic: Characteristic, callback: F) -> Result<Subscription, TapError>
     where
-        F: Fn(Uuid, Vec<u8>) + Send + 'static,
+        F: Fn(EventSource, &[u8]) + Send + Sync + 'static,
     {
-        if let Some(peripheral_arc) = &self.peripheral {
-            let characteristics = {
-                let peripheral = peripheral_arc.lock().await;
-                peripheral.characteristics().clone()
-            };
-            for characteristic in &characteristics {
-                if characteristic.uuid == characteristic_uuid {
-                    if !characteristic.properties.contains(btleplug::api::CharPropFlags::NOTIFY) {
-                        return Err(format!("Characteristic {:?} does not support notifications", characteristic_uuid).into());
-                    }
-                    {
-                        let peripheral = peripheral_arc.lock().await;
-                        peripheral.subscribe(characteristic).await?;
-                    }
-                    let peripheral_clone = Arc::clone(peripheral_arc);
-                    let char_uuid = characteristic.uuid;
-                    tokio::spawn(async move {
-                        let stream = {
-                            let peripheral = peripheral_clone.lock().await;
-                            peripheral.notifications().await
-                        };
-                        if let Ok(mut stream) = stream {
-                            println!("Successfully subscribed to characteristic {:?}", char_uuid);
-                            while let Some(notification) = stream.next().await {
-                                if notification.uuid == char_uuid {
-                                    callback(char_uuid, notification.value);
-                                }
-                            }
-                        } else {
-                            eprintln!("Failed to get notification stream for characteristic {:?}", char_uuid);
-                        }
-                    });
-                    return Ok(());
-                }
+        let listener = Listener::Callback(Arc::new(callback));
+        let id = self.listen(characteristic, listener).await?;
+        Ok(self.subscription(vec![id]))
+    }
+
+    async fn listen(&self, characteristic: Characteristic, listener: Listener) -> Result<u64, TapError> {
+        let transport = self.transport()?;
+        self.start_dispatcher(transport.as_ref(), false).await?;
+        let gatt = Arc::clone(&self.registry.lock().unwrap().gatt);
+        let _gatt = gatt.lock().await;
+        let already_subscribed = self.registry.lock().unwrap().subscribed.contains(&characteristic);
+        if !already_subscribed {
+            subscribe(transport.as_ref(), characteristic).await?;
+        }
+
+        let mut registry = self.registry.lock().unwrap();
+        registry.subscribed.insert(characteristic);
+        Ok(registry.add(Target::Notification { characteristic, listener }))
+    }
+
+    // The notification stream is opened before anything is subscribed, so no packet is missed.
+    async fn start_dispatcher(&self, transport: &dyn TapTransport, restart: bool) -> Result<(), TapError> {
+        {
+            let mut registry = self.registry.lock().unwrap();
+            if restart {
+                registry.stop_dispatcher();
+            } else if registry.dispatcher.is_some() {
+                return Ok(());
             }
-            Err(format!("Characteristic {:?} not found", characteristic_uuid).into())
-        } else {
-            Err("No peripheral connected".into())
         }
+
+        let stream = transport.notifications().await?;
+        let device = DeviceId::from(transport.address());
+        let name = transport.name().await;
+        let task = tokio::spawn(dispatch(Arc::downgrade(&self.registry), stream, device, name));
+
+        let mut registry = self.registry.lock().unwrap();
+        registry.stop_dispatcher();
+        registry.dispatcher = Some(task.abort_handle());
+        Ok(())
     }
 
-    pub async fn register_air_gesture_events(&self, listener: fn(Uuid, AirGestures)) {
-        let air_gesture_uuid = Uuid::from_u128(Characteristic::AirGestureData.as_uuid());
-        let callback = move |uuid: Uuid, data: Vec<u8>| {
-            if data.len() > 0 {
-                let gesture_value = data[0] as u8;
-                let gesture = AirGestures::from_u8(gesture_value);
-                listener(uuid, gesture);
+    // Every event of the device as a stream. When the stream is not polled,
+    // its buffer fills up and further events are dropped until it has room,
+    // then a `TapEvent::Lagged` reports how many were missed. Other listeners
+    // keep running. Dropping the stream removes its listeners.
+    pub async fn events(&self) -> Result<EventStream, TapError> {
+        let transport = self.transport()?;
+        let (sender, receiver) = mpsc::channel(EVENT_STREAM_CAPACITY);
+        let sender = Arc::new(StreamSender { sender, missed: AtomicU64::new(0) });
+
+        // Dropped on an error, which removes the listeners added so far.
+        let mut subscription = self.subscription(Vec::new());
+        // Only what the device has and can notify, e.g. Tap Strap has no air gesture
+        // characteristic and notifications are optional for the battery level.
+        for characteristic in [
+            Characteristic::TapData,
+            Characteristic::MouseData,
+            Characteristic::AirGestureData,
+            Characteristic::RawSensors,
+            Characteristic::BatteryLevel,
+        ] {
+            let gatt_characteristic = transport.find_characteristic(Uuid::from_u128(characteristic.as_uuid()));
+            if gatt_characteristic.is_some_and(|gatt_characteristic| gatt_characteristic.properties.contains(CharPropFlags::NOTIFY)) {
+                subscription.ids.push(self.listen(characteristic, Listener::Stream(sender.clone())).await?);
+            }
+        }
+
+        let mut connection_events = transport.connection_events().await?;
+        let device = DeviceId::from(transport.address());
+        let name = transport.name().await;
+        let task = tokio::spawn(async move {
+            while let Some(state) = connection_events.next().await {
+                if sender.is_closed() {
+                    break;
+                }
+                let source = EventSource::new(device, name.clone());
+                sender.send(TapEvent::Connection { source, state });
             }
+        });
+        subscription.ids.push(self.registry.lock().unwrap().add(Target::Task(task.abort_handle())));
+
+        Ok(Box::pin(SubscribedStream { receiver, _subscription: subscription }))
+    }
+
+    // Opens a new notification stream and subscribes every listened
+    // characteristic again, e.g. after the transport reconnected.
+    pub async fn resubscribe(&mut self) -> Result<(), TapError> {
+        let transport = self.transport()?;
+        let gatt = Arc::clone(&self.registry.lock().unwrap().gatt);
+        let _gatt = gatt.lock().await;
+        let characteristics = {
+            let mut registry = self.registry.lock().unwrap();
+            registry.subscribed.clear();
+            registry.listened_characteristics()
         };
-        if let Err(e) = self.subscribe_to_characteristic(air_gesture_uuid, callback).await {
-            eprintln!("Failed to register air gesture events: {:?}", e);
+        if characteristics.is_empty() {
+            return Ok(());
+        }
+
+        self.start_dispatcher(transport.as_ref(), true).await?;
+        for characteristic in characteristics {
+            subscribe(transport.as_ref(), characteristic).await?;
+            self.registry.lock().unwrap().subscribed.insert(characteristic);
         }
+        Ok(())
     }
 
-    pub async fn register_tap_events(&self, listener: fn(Uuid, u8)) {
-        let tap_data_uuid = Uuid::from_u128(Characteristic::TapData.as_uuid());
-        let callback = move |uuid: Uuid, data: Vec<u8>| {
-            if data.len() > 0 {
-                let tapcode = data[0];
-                listener(uuid, tapcode);
+    async fn subscribe_to_connection_state<F>(&self, connection_state: ConnectionState, callback: F) -> Result<Subscription, TapError>
+    where
+        F: Fn(EventSource) + Send + Sync + 'static,
+    {
+        let transport = self.transport()?;
+        let mut stream = transport.connection_events().await?;
+        let device = DeviceId::from(transport.address());
+        let name = transport.name().await;
+        let task = tokio::spawn(async move {
+            while let Some(state) = stream.next().await {
+                if state == connection_state {
+                    callback(EventSource::new(device, name.clone()));
+                }
+            }
+        });
+        Ok(self.track_task(task.abort_handle()))
+    }
+
+    pub async fn register_connection_events(&self, listener: impl Fn(EventSource) + Send + Sync + 'static) -> Result<Subscription, TapError> {
+        self.subscribe_to_connection_state(ConnectionState::Connected, listener).await
+    }
+
+    pub async fn register_disconnection_events(&self, listener: impl Fn(EventSource) + Send + Sync + 'static) -> Result<Subscription, TapError> {
+        self.subscribe_to_connection_state(ConnectionState::Disconnected, listener).await
+    }
+
+    pub async fn register_air_gesture_events(&self, listener: impl Fn(EventSource, AirGestures) + Send + Sync + 'static) -> Result<Subscription, TapError> {
+        let callback = move |source: EventSource, data: &[u8]| {
+            if let Some(gesture) = decode_air_gesture(data) {
+                listener(source, gesture);
             }
         };
-        if let Err(e) = self.subscribe_to_characteristic(tap_data_uuid, callback).await {
-            eprintln!("Failed to register tap events: {:?}", e);
-        }
+        self.subscribe_to_characteristic(Characteristic::AirGestureData, callback).await
     }
 
-    pub async fn register_raw_data_events(&self, listener: fn(Uuid, Vec<u8>)) {
-        let raw_sensors_uuid = Uuid::from_u128(Characteristic::RawSensors.as_uuid());
-        let callback = move |uuid: Uuid, data: Vec<u8>| {
-            listener(uuid, data);
+    pub async fn register_tap_events(&self, listener: impl Fn(EventSource, TapCode) + Send + Sync + 'static) -> Result<Subscription, TapError> {
+        let callback = move |source: EventSource, data: &[u8]| {
+            if let Some(tapcode) = decode_tap(data) {
+                listener(source, tapcode);
+            }
         };
-        if let Err(e) = self.subscribe_to_characteristic(raw_sensors_uuid, callback).await {
-            eprintln!("Failed to register raw data events: {:?}", e);
-        }
+        self.subscribe_to_characteristic(Characteristic::TapData, callback).await
+    }
+
+    pub async fn register_raw_data_events(&self, listener: impl Fn(EventSource, Vec<u8>) + Send + Sync + 'static) -> Result<Subscription, TapError> {
+        let callback = move |source: EventSource, data: &[u8]| {
+            listener(source, data.to_vec());
+        };
+        self.subscribe_to_characteristic(Characteristic::RawSensors, callback).await
+    }
+
+    pub async fn register_raw_sensor_events(&self, listener: impl Fn(EventSource, RawSensorPacket) + Send + Sync + 'static) -> Result<Subscription, TapError> {
+        let callback = move |source: EventSource, data: &[u8]| {
+            listener(source, RawSensorPacket::parse(data));
+        };
+        self.subscribe_to_characteristic(Characteristic::RawSensors, callback).await
     }
 
-    pub async fn register_mouse_events(&self, listener: fn(Uuid, i16, i16, bool)) {
-        let mouse_data_uuid = Uuid::from_u128(Characteristic::MouseData.as_uuid());
-        let callback = move |uuid: Uuid, data: Vec<u8>| {
-            if data.len() >= 4 {
-                let vx = ((data[0] as i16) << 8) | (data[1] as i16);
-                let vy = ((data[2] as i16) << 8) | (data[3] as i16);
-                let proximity = data.len() > 4 && data[4] != 0;
-                listener(uuid, vx, vy, proximity);
+    pub async fn register_mouse_events(&self, listener: impl Fn(EventSource, i16, i16, bool) + Send + Sync + 'static) -> Result<Subscription, TapError> {
+        let callback = move |source: EventSource, data: &[u8]| {
+            if let Some((vx, vy, proximity)) = decode_mouse(data) {
+                listener(source, vx, vy, proximity);
             }
         };
-        if let Err(e) = self.subscribe_to_characteristic(mouse_data_uuid, callback).await {
-            eprintln!("Failed to register mouse events: {:?}", e);
-        }
+        self.subscribe_to_characteristic(Characteristic::MouseData, callback).await
+    }
+
+    pub async fn register_air_gesture_state_events(&self, listener: impl Fn(EventSource, bool) + Send + Sync + 'static) -> Result<Subscription, TapError> {
+        let callback = move |source: EventSource, data: &[u8]| {
+            if let Some(in_air_gesture_state) = decode_air_gesture_state(data) {
+                listener(source, in_air_gesture_state);
+            }
+        };
+        self.subscribe_to_characteristic(Characteristic::AirGestureData, callback).await
     }
 
-    pub async fn register_air_gesture_state_events(&self, listener: fn(Uuid, bool)) {
-        let air_gesture_uuid = Uuid::from_u128(Characteristic::AirGestureData.as_uuid());
-        let callback = move |uuid: Uuid, data: Vec<u8>| {
-            if data.len() > 0 {
-                let in_air_gesture_state = data[0] == 0x14 && data.len() > 1 && data[1] != 0;
-                listener(uuid, in_air_gesture_state);
+    pub async fn register_battery_events(&self, listener: impl Fn(EventSource, u8) + Send + Sync + 'static) -> Result<Subscription, TapError> {
+        let callback = move |source: EventSource, data: &[u8]| {
+            if let Some(level) = decode_battery(data) {
+                listener(source, level);
             }
         };
-        if let Err(e) = self.subscribe_to_characteristic(air_gesture_uuid, callback).await {
-            eprintln!("Failed to register air gesture state events: {:?}", e);
+        self.subscribe_to_characteristic(Characteristic::BatteryLevel, callback).await
+    }
+}
+
+async fn subscribe(transport: &dyn TapTransport, tap_characteristic: Characteristic) -> Result<(), TapError> {
+    let characteristic = transport
+        .find_characteristic(Uuid::from_u128(tap_characteristic.as_uuid()))
+        .ok_or(TapError::CharacteristicMissing(tap_characteristic))?;
+    if !characteristic.properties.contains(CharPropFlags::NOTIFY) {
+        return Err(TapError::Protocol(format!("Characteristic {:?} does not support notifications", tap_characteristic)));
+    }
+
+    transport.subscribe(&characteristic).await?;
+    Ok(())
+}
+
+// Reads the notification stream once and hands every packet to the listeners
+// of its characteristic. Stream events are decoded once per packet.
+async fn dispatch(registry: Weak<StdMutex<Registry>>, mut stream: NotificationStream, device: DeviceId, name: Option<String>) {
+    while let Some(notification) = stream.next().await {
+        let Ok(characteristic) = Characteristic::from_uuid(notification.uuid) else {
+            continue;
+        };
+        let Some(registry) = registry.upgrade() else {
+            return;
+        };
+        let (listeners, recorder) = {
+            let registry = registry.lock().unwrap();
+            (registry.listeners(characteristic), registry.recorder.clone())
+        };
+        drop(registry);
+        if let Some(recorder) = recorder {
+            recorder.record_notification(characteristic, &notification.value);
+        }
+        if listeners.is_empty() {
+            continue;
+        }
+
+        let source = EventSource::new(device, name.clone());
+        let mut event = None;
+        for listener in listeners {
+            match listener {
+                Listener::Callback(callback) => callback(source.clone(), &notification.value),
+                Listener::Stream(sender) => {
+                    let event = event.get_or_insert_with(|| TapEvent::decode(characteristic, source.clone(), &notification.value));
+                    if let Some(event) = event {
+                        sender.send(event.clone());
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transport::SimulatedTap;
+    use std::sync::atomic::AtomicUsize;
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn dropping_the_last_subscription_unsubscribes() {
+        let simulated_tap = Arc::new(SimulatedTap::new());
+        simulated_tap.connect().await.unwrap();
+        let mut event_manager = EventManager::new();
+        event_manager.set_transport(simulated_tap.clone());
+
+        let first = event_manager.register_tap_events(|_, _| {}).await.unwrap();
+        let second = event_manager.register_tap_events(|_, _| {}).await.unwrap();
+        assert!(simulated_tap.is_subscribed(Characteristic::TapData));
+
+        drop(first);
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert!(simulated_tap.is_subscribed(Characteristic::TapData));
+
+        second.cancel();
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert!(!simulated_tap.is_subscribed(Characteristic::TapData));
+    }
+
+    #[tokio::test]
+    async fn registering_again_after_a_drop_stays_subscribed() {
+        let simulated_tap = Arc::new(SimulatedTap::new());
+        simulated_tap.connect().await.unwrap();
+        let mut event_manager = EventManager::new();
+        event_manager.set_transport(simulated_tap.clone());
+
+        drop(event_manager.register_tap_events(|_, _| {}).await.unwrap());
+        let taps = Arc::new(AtomicUsize::new(0));
+        let counter = Arc::clone(&taps);
+        let _subscription = event_manager
+            .register_tap_events(move |_, _| {
+                counter.fetch_add(1, Ordering::SeqCst);
+            })
+            .await
+            .unwrap();
+        // Lets the unsubscribe of the dropped subscription run.
+        tokio::time::sleep(Duration::from_millis(50)).await;
+
+        assert!(simulated_tap.is_subscribed(Characteristic::TapData));
+        simulated_tap.push_tap(1);
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert_eq!(taps.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn full_stream_drops_events_and_reports_them() {
+        let simulated_tap = Arc::new(SimulatedTap::new());
+        simulated_tap.connect().await.unwrap();
+        let mut event_manager = EventManager::new();
+        event_manager.set_transport(simulated_tap.clone());
+
+        let mut events = event_manager.events().await.unwrap();
+        let taps = Arc::new(AtomicUsize::new(0));
+        let counter = Arc::clone(&taps);
+        let _subscription = event_manager
+            .register_tap_events(move |_, _| {
+                counter.fetch_add(1, Ordering::SeqCst);
+            })
+            .await
+            .unwrap();
+
+        let pushed = EVENT_STREAM_CAPACITY + 10;
+        for _ in 0..pushed {
+            simulated_tap.push_tap(1);
+        }
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        // The unpolled stream doesn't hold up the callbacks.
+        assert_eq!(taps.load(Ordering::SeqCst), pushed);
+
+        for _ in 0..EVENT_STREAM_CAPACITY {
+            assert!(matches!(events.next().await, Some(TapEvent::Tap { .. })));
         }
+        simulated_tap.push_tap(1);
+        assert!(matches!(events.next().await, Some(TapEvent::Lagged { missed: 10, .. })));
+        assert!(matches!(events.next().await, Some(TapEvent::Tap { .. })));
     }
 }
@@ -0,0 +1,269 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt;
+use std::path::Path;
+
+use crate::error::TapError;
+use crate::types::air_gestures::AirGestures;
+use crate::types::input_type::{InputMode, InputType};
+use crate::types::tap_code::TapCode;
+use crate::Client;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum KeymapAction {
+    Text(String),
+    Keys(Vec<String>),
+    Vibrate(Vec<u16>),
+    InputMode(InputMode),
+    InputType(InputType),
+    Layer(String),
+    OneShotLayer(String),
+    Callback(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+pub struct KeymapLayer {
+    pub name: String,
+    #[serde(default)]
+    pub taps: HashMap<TapCode, KeymapAction>,
+    #[serde(default)]
+    pub gestures: HashMap<AirGestures, KeymapAction>,
+}
+
+// [markdown]
+// `Keymap` binds tap codes and air gestures to actions, grouped in layers.
+// It is loaded from TOML or JSON, taps are written as finger strings:
+// ```toml
+// default_layer = "base"
+//
+// [[layers]]
+// name = "base"
+// taps = { "10000" = { text = "a" }, "01111" = { layer = "nav" } }
+// gestures = { UpOneFinger = { vibrate = [100] } }
+// ```
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Keymap {
+    pub default_layer: String,
+    pub layers: Vec<KeymapLayer>,
+}
+
+impl Keymap {
+    pub fn from_toml_str(source: &str) -> Result<Self, TapError> {
+        let keymap: Keymap = toml::from_str(source).map_err(|e| TapError::Protocol(format!("Invalid TOML keymap: {}", e)))?;
+        keymap.validate()?;
+        Ok(keymap)
+    }
+
+    pub fn from_json_str(source: &str) -> Result<Self, TapError> {
+        let keymap: Keymap = serde_json::from_str(source).map_err(|e| TapError::Protocol(format!("Invalid JSON keymap: {}", e)))?;
+        keymap.validate()?;
+        Ok(keymap)
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, TapError> {
+        let path = path.as_ref();
+        let source = std::fs::read_to_string(path)?;
+        match path.extension().and_then(|extension| extension.to_str()) {
+            Some("json") => Keymap::from_json_str(&source),
+            Some("toml") => Keymap::from_toml_str(&source),
+            _ => Err(TapError::Protocol(format!("Unknown keymap format for {:?}, expected .toml or .json", path))),
+        }
+    }
+
+    pub fn layer(&self, name: &str) -> Option<&KeymapLayer> {
+        self.layers.iter().find(|layer| layer.name == name)
+    }
+
+    fn validate(&self) -> Result<(), TapError> {
+        if self.layer(&self.default_layer).is_none() {
+            return Err(TapError::Protocol(format!("Default layer {:?} is not defined", self.default_layer)));
+        }
+
+        for layer in &self.layers {
+            for action in layer.taps.values().chain(layer.gestures.values()) {
+                if let KeymapAction::Layer(target) | KeymapAction::OneShotLayer(target) = action {
+                    if self.layer(target).is_none() {
+                        return Err(TapError::Protocol(format!("Layer {:?} switches to undefined layer {:?}", layer.name, target)));
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+type KeymapCallback = Box<dyn Fn() + Send + Sync>;
+
+// [markdown]
+// `KeymapEngine` resolves taps and air gestures against a `Keymap`.
+// Layer switches and named callbacks are handled by the engine itself,
+// every other action is returned to the caller, and SDK commands can be
+// sent to the device with `execute`.
+pub struct KeymapEngine {
+    keymap: Keymap,
+    active_layer: String,
+    one_shot_layer: Option<String>,
+    callbacks: HashMap<String, KeymapCallback>,
+}
+
+impl KeymapEngine {
+    pub fn new(keymap: Keymap) -> Self {
+        KeymapEngine {
+            active_layer: keymap.default_layer.clone(),
+            keymap,
+            one_shot_layer: None,
+            callbacks: HashMap::new(),
+        }
+    }
+
+    pub fn register_callback(&mut self, name: &str, callback: impl Fn() + Send + Sync + 'static) {
+        self.callbacks.insert(name.to_string(), Box::new(callback));
+    }
+
+    pub fn active_layer(&self) -> &str {
+        self.one_shot_layer.as_deref().unwrap_or(&self.active_layer)
+    }
+
+    pub fn reset(&mut self) {
+        self.active_layer = self.keymap.default_layer.clone();
+        self.one_shot_layer = None;
+    }
+
+    pub fn on_tap(&mut self, tap_code: impl Into<TapCode>) -> Option<KeymapAction> {
+        let tap_code = tap_code.into();
+        let action = self
+            .keymap
+            .layer(self.active_layer())
+            .and_then(|layer| layer.taps.get(&tap_code))
+            .cloned();
+        self.handle(action)
+    }
+
+    pub fn on_air_gesture(&mut self, gesture: AirGestures) -> Option<KeymapAction> {
+        let action = self
+            .keymap
+            .layer(self.active_layer())
+            .and_then(|layer| layer.gestures.get(&gesture))
+            .cloned();
+        self.handle(action)
+    }
+
+    fn handle(&mut self, action: Option<KeymapAction>) -> Option<KeymapAction> {
+        // A one-shot layer only lasts for a single tap or gesture.
+        self.one_shot_layer = None;
+
+        match action? {
+            KeymapAction::Layer(name) => {
+                self.active_layer = name;
+                None
+            }
+            KeymapAction::OneShotLayer(name) => {
+                self.one_shot_layer = Some(name);
+                None
+            }
+            KeymapAction::Callback(name) => {
+                match self.callbacks.get(&name) {
+                    Some(callback) => callback(),
+                    None => eprintln!("No keymap callback registered for {:?}", name),
+                }
+                None
+            }
+            action => Some(action),
+        }
+    }
+
+    // Sends SDK commands to the device. Returns false for actions the
+    // application has to handle itself, such as text and key combos.
+    pub async fn execute(&self, client: &mut Client, action: &KeymapAction) -> Result<bool, TapError> {
+        match action {
+            KeymapAction::Vibrate(sequence) => client.send_vibration_sequence(sequence.clone()).await?,
+            KeymapAction::InputMode(mode) => client.set_input_mode(*mode, None).await?,
+            KeymapAction::InputType(input_type) => client.set_input_type(*input_type).await?,
+            _ => return Ok(false),
+        }
+        Ok(true)
+    }
+}
+
+impl fmt::Debug for KeymapEngine {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("KeymapEngine")
+            .field("keymap", &self.keymap)
+            .field("active_layer", &self.active_layer)
+            .field("one_shot_layer", &self.one_shot_layer)
+            .field("callbacks", &self.callbacks.keys().collect::<Vec<_>>())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TOML_KEYMAP: &str = r#"
+default_layer = "base"
+
+[[layers]]
+name = "base"
+taps = { "10000" = { text = "a" }, "01111" = { layer = "nav" }, "00111" = { one_shot_layer = "nav" } }
+gestures = { UpOneFinger = { vibrate = [100] } }
+
+[[layers]]
+name = "nav"
+taps = { "10000" = { keys = ["ctrl", "c"] }, "01111" = { layer = "base" } }
+"#;
+
+    #[test]
+    fn loads_toml() {
+        let keymap = Keymap::from_toml_str(TOML_KEYMAP).unwrap();
+        assert_eq!(keymap.default_layer, "base");
+        let base = keymap.layer("base").unwrap();
+        assert_eq!(base.taps.get(&"10000".parse().unwrap()), Some(&KeymapAction::Text(String::from("a"))));
+        assert_eq!(base.gestures.get(&AirGestures::UpOneFinger), Some(&KeymapAction::Vibrate(vec![100])));
+    }
+
+    #[test]
+    fn loads_json() {
+        let json = serde_json::to_string(&Keymap::from_toml_str(TOML_KEYMAP).unwrap()).unwrap();
+        assert_eq!(Keymap::from_json_str(&json).unwrap(), Keymap::from_toml_str(TOML_KEYMAP).unwrap());
+    }
+
+    #[test]
+    fn rejects_invalid_keymaps() {
+        let missing_default = TOML_KEYMAP.replace("default_layer = \"base\"", "default_layer = \"home\"");
+        assert!(matches!(Keymap::from_toml_str(&missing_default), Err(TapError::Protocol(_))));
+
+        let undefined_layer = TOML_KEYMAP.replace("{ layer = \"nav\" }", "{ layer = \"symbols\" }");
+        assert!(matches!(Keymap::from_toml_str(&undefined_layer), Err(TapError::Protocol(_))));
+
+        let invalid_tap_code = TOML_KEYMAP.replace("\"10000\" = { text", "\"1000\" = { text");
+        assert!(matches!(Keymap::from_toml_str(&invalid_tap_code), Err(TapError::Protocol(_))));
+
+        assert!(matches!(Keymap::from_json_str("{"), Err(TapError::Protocol(_))));
+
+        let path = std::env::temp_dir().join(format!("tap-sdk-keymap-{}.yaml", std::process::id()));
+        std::fs::write(&path, TOML_KEYMAP).unwrap();
+        let unknown_format = Keymap::load(&path);
+        std::fs::remove_file(&path).unwrap();
+        assert!(matches!(unknown_format, Err(TapError::Protocol(_))));
+        assert!(matches!(Keymap::load(&path), Err(TapError::Io(_))));
+    }
+
+    #[test]
+    fn switches_layers() {
+        let mut engine = KeymapEngine::new(Keymap::from_toml_str(TOML_KEYMAP).unwrap());
+        assert_eq!(engine.on_tap(0b00001), Some(KeymapAction::Text(String::from("a"))));
+
+        assert_eq!(engine.on_tap(0b11110), None);
+        assert_eq!(engine.active_layer(), "nav");
+        assert_eq!(engine.on_tap(0b00001), Some(KeymapAction::Keys(vec![String::from("ctrl"), String::from("c")])));
+        engine.reset();
+
+        assert_eq!(engine.on_tap(0b11100), None);
+        assert_eq!(engine.active_layer(), "nav");
+        engine.on_tap(0b00001);
+        assert_eq!(engine.active_layer(), "base");
+    }
+}
@@ -1,7 +1,32 @@
+mod error;
+pub use self::error::TapError;
 
 mod client;
-pub use self::client::Client;
+pub use self::client::{Client, DEFAULT_AUTO_REFRESH_INTERVAL, MIN_AUTO_REFRESH_INTERVAL};
+
+mod client_builder;
+pub use self::client_builder::ClientBuilder;
+
+mod discovery;
+pub use self::discovery::{discover, DeviceSelector, DiscoveredTap, DiscoveryOptions};
+
+mod tap_manager;
+pub use self::tap_manager::{ManagerSubscription, TapManager};
+
+mod reconnect;
+pub use self::reconnect::ReconnectPolicy;
 
 pub mod types;
 
 pub mod event_manager;
+pub use self::event_manager::Subscription;
+
+pub mod transport;
+
+pub mod recorder;
+pub use self::recorder::SessionRecorder;
+
+
+pub mod tap_map;
+
+pub mod keymap;
@@ -0,0 +1,102 @@
+use std::time::Duration;
+
+// [markdown]
+// `ReconnectPolicy` controls how a `Client` reconnects after the Tap drops
+// its link. The delay before each attempt grows by `backoff_factor`, up to
+// `max_delay`. With `max_attempts` set to `None` the client never gives up.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReconnectPolicy {
+    pub initial_delay: Duration,
+    pub max_delay: Duration,
+    pub backoff_factor: u32,
+    pub max_attempts: Option<u32>,
+}
+
+impl ReconnectPolicy {
+    pub fn delay(&self, attempt: u32) -> Duration {
+        self.initial_delay
+            .saturating_mul(self.backoff_factor.saturating_pow(attempt))
+            .min(self.max_delay)
+    }
+}
+
+impl Default for ReconnectPolicy {
+    fn default() -> Self {
+        ReconnectPolicy {
+            initial_delay: Duration::from_secs(1),
+            max_delay: Duration::from_secs(30),
+            backoff_factor: 2,
+            max_attempts: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client_builder::ClientBuilder;
+    use crate::transport::SimulatedTap;
+    use crate::types::uuid::characteristics::Characteristic;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    #[test]
+    fn delay_grows_up_to_the_maximum() {
+        let policy = ReconnectPolicy::default();
+        assert_eq!(policy.delay(0), Duration::from_secs(1));
+        assert_eq!(policy.delay(3), Duration::from_secs(8));
+        assert_eq!(policy.delay(10), Duration::from_secs(30));
+        assert_eq!(policy.delay(u32::MAX), Duration::from_secs(30));
+    }
+
+    // Polls until the condition holds, the client reconnects from background tasks.
+    async fn wait_for(condition: impl Fn() -> bool) -> bool {
+        for _ in 0..100 {
+            if condition() {
+                return true;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        false
+    }
+
+    #[tokio::test]
+    async fn reconnect_restores_subscriptions_and_mode() {
+        let simulated_tap = Arc::new(SimulatedTap::new());
+        let policy = ReconnectPolicy {
+            initial_delay: Duration::from_millis(10),
+            max_delay: Duration::from_millis(10),
+            backoff_factor: 1,
+            max_attempts: None,
+        };
+        let tap_client = ClientBuilder::new()
+            .transport(simulated_tap.clone())
+            .auto_refresh_interval(None)
+            .reconnect_policy(Some(policy))
+            .connect()
+            .await
+            .unwrap();
+
+        let taps = Arc::new(AtomicUsize::new(0));
+        let counter = Arc::clone(&taps);
+        let _subscription = tap_client
+            .register_tap_events(move |_, _| {
+                counter.fetch_add(1, Ordering::SeqCst);
+            })
+            .await
+            .unwrap();
+
+        // Lets the reconnect watcher start listening before the link drops.
+        tokio::task::yield_now().await;
+        simulated_tap.set_in_range(false);
+        assert!(!simulated_tap.is_subscribed(Characteristic::TapData));
+        simulated_tap.clear_written_commands();
+        simulated_tap.set_in_range(true);
+        assert!(wait_for(|| simulated_tap.is_subscribed(Characteristic::TapData)).await);
+        assert!(wait_for(|| !simulated_tap.written_commands().is_empty()).await);
+        assert_eq!(simulated_tap.written_commands()[0].0, Characteristic::TapMode);
+
+        simulated_tap.push_tap(1);
+        assert!(wait_for(|| taps.load(Ordering::SeqCst) == 1).await);
+    }
+}
@@ -0,0 +1,134 @@
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::{LineWriter, Write};
+use std::path::Path;
+use std::sync::mpsc::{self, Sender};
+use std::thread::{self, JoinHandle};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::error::TapError;
+use crate::types::device::DeviceId;
+use crate::types::uuid::characteristics::Characteristic;
+
+pub const RECORDING_FORMAT: &str = "tap-session";
+pub const RECORDING_VERSION: u32 = 1;
+
+// First line of a recording.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RecordingHeader {
+    pub format: String,
+    pub version: u32,
+    pub device: String,
+    pub name: Option<String>,
+    // Microseconds since the UNIX epoch, host clock.
+    pub started_at: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RecordKind {
+    // Received from the device.
+    Notification,
+    // Written to the device.
+    Command,
+}
+
+// Every line after the header.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Record {
+    pub kind: RecordKind,
+    pub characteristic: Characteristic,
+    pub device: String,
+    // Microseconds since the UNIX epoch, host clock.
+    pub timestamp: u64,
+    pub data: Vec<u8>,
+}
+
+// [markdown]
+// `SessionRecorder` writes a Tap session to a JSONL file: a header line,
+// then one line per notification received and command written, e.g.
+// ```json
+// {"format":"tap-session","version":1,"device":"D1:2A:58:0C:1E:77","name":"Tap_1","started_at":1760000000000000}
+// {"kind":"notification","characteristic":"TapData","device":"D1:2A:58:0C:1E:77","timestamp":1760000000120000,"data":[1]}
+// {"kind":"command","characteristic":"TapMode","device":"D1:2A:58:0C:1E:77","timestamp":1760000000130000,"data":[3,12,0,1]}
+// ```
+// Lines are flushed as they are written, so a crash keeps the session so far.
+#[derive(Debug)]
+pub struct SessionRecorder {
+    device: String,
+    // Records are written by a thread of their own, so the dispatcher never waits for the disk.
+    sender: Option<Sender<Record>>,
+    writer: Option<JoinHandle<()>>,
+}
+
+impl SessionRecorder {
+    pub fn create(path: impl AsRef<Path>, device: DeviceId, name: Option<String>) -> Result<Self, TapError> {
+        let mut file = LineWriter::new(File::create(path)?);
+        let header = RecordingHeader {
+            format: RECORDING_FORMAT.to_string(),
+            version: RECORDING_VERSION,
+            device: device.to_string(),
+            name,
+            started_at: timestamp(SystemTime::now()),
+        };
+        write_line(&mut file, &header)?;
+
+        let (sender, receiver) = mpsc::channel::<Record>();
+        let writer = thread::spawn(move || {
+            for record in receiver {
+                if let Err(e) = write_line(&mut file, &record) {
+                    eprintln!("Failed to record {:?} {:?}: {:?}", record.kind, record.characteristic, e);
+                }
+            }
+        });
+
+        Ok(SessionRecorder {
+            device: device.to_string(),
+            sender: Some(sender),
+            writer: Some(writer),
+        })
+    }
+
+    pub fn record_notification(&self, characteristic: Characteristic, data: &[u8]) {
+        self.record(RecordKind::Notification, characteristic, data);
+    }
+
+    pub fn record_command(&self, characteristic: Characteristic, data: &[u8]) {
+        self.record(RecordKind::Command, characteristic, data);
+    }
+
+    // A failed write is reported by the writer, but never interrupts the session.
+    fn record(&self, kind: RecordKind, characteristic: Characteristic, data: &[u8]) {
+        let record = Record {
+            kind,
+            characteristic,
+            device: self.device.clone(),
+            timestamp: timestamp(SystemTime::now()),
+            data: data.to_vec(),
+        };
+        if let Some(sender) = &self.sender {
+            let _ = sender.send(record);
+        }
+    }
+}
+
+// Waits for the queued records, so the file is complete once the recorder is gone.
+impl Drop for SessionRecorder {
+    fn drop(&mut self) {
+        self.sender.take();
+        if let Some(writer) = self.writer.take() {
+            let _ = writer.join();
+        }
+    }
+}
+
+fn write_line(file: &mut impl Write, line: &impl Serialize) -> Result<(), TapError> {
+    let mut line = serde_json::to_vec(line).map_err(|e| TapError::Protocol(e.to_string()))?;
+    line.push(b'\n');
+    file.write_all(&line)?;
+    Ok(())
+}
+
+fn timestamp(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH).unwrap_or_default().as_micros() as u64
+}
@@ -0,0 +1,257 @@
+use btleplug::api::BDAddr;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::sync::{Arc, Mutex as StdMutex, Weak};
+
+use crate::discovery::{discover, DiscoveryOptions};
+use crate::error::TapError;
+use crate::event_manager::Subscription;
+use crate::types::air_gestures::AirGestures;
+use crate::types::device::{DeviceId, EventSource};
+use crate::types::input_type::{InputMode, InputType};
+use crate::types::mouse_mode::MouseModes;
+use crate::types::raw_sensors::RawSensorPacket;
+use crate::types::tap_code::TapCode;
+use crate::Client;
+
+// Listeners are shared between every managed device.
+#[derive(Clone)]
+enum ManagerListener {
+    AirGesture(Arc<dyn Fn(EventSource, AirGestures) + Send + Sync>),
+    Tap(Arc<dyn Fn(EventSource, TapCode) + Send + Sync>),
+    RawData(Arc<dyn Fn(EventSource, Vec<u8>) + Send + Sync>),
+    RawSensor(Arc<dyn Fn(EventSource, RawSensorPacket) + Send + Sync>),
+    Mouse(Arc<dyn Fn(EventSource, i16, i16, bool) + Send + Sync>),
+    AirGestureState(Arc<dyn Fn(EventSource, bool) + Send + Sync>),
+    Connection(Arc<dyn Fn(EventSource) + Send + Sync>),
+    Disconnection(Arc<dyn Fn(EventSource) + Send + Sync>),
+}
+
+impl fmt::Debug for ManagerListener {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let kind = match self {
+            ManagerListener::AirGesture(_) => "AirGesture",
+            ManagerListener::Tap(_) => "Tap",
+            ManagerListener::RawData(_) => "RawData",
+            ManagerListener::RawSensor(_) => "RawSensor",
+            ManagerListener::Mouse(_) => "Mouse",
+            ManagerListener::AirGestureState(_) => "AirGestureState",
+            ManagerListener::Connection(_) => "Connection",
+            ManagerListener::Disconnection(_) => "Disconnection",
+        };
+        f.write_str(kind)
+    }
+}
+
+// Shared with every `ManagerSubscription`, so dropping one removes its
+// listener from all devices without going through the `TapManager`.
+#[derive(Debug, Default)]
+struct ManagerListeners {
+    next_id: u64,
+    listeners: Vec<(u64, ManagerListener)>,
+    // The subscription of every manager listener attached to a device.
+    subscriptions: BTreeMap<DeviceId, Vec<(u64, Subscription)>>,
+}
+
+// [markdown]
+// `ManagerSubscription` is returned by the `register_*` calls of `TapManager`.
+// It covers the listener on every managed device, including devices added
+// later, and dropping it or calling `cancel()` removes it from all of them.
+#[must_use = "the listener is removed as soon as the subscription is dropped"]
+#[derive(Debug)]
+pub struct ManagerSubscription {
+    listeners: Weak<StdMutex<ManagerListeners>>,
+    id: u64,
+}
+
+impl ManagerSubscription {
+    pub fn cancel(self) {}
+}
+
+impl Drop for ManagerSubscription {
+    fn drop(&mut self) {
+        let Some(listeners) = self.listeners.upgrade() else {
+            return;
+        };
+        // Dropped after the lock is released, which unsubscribes the devices.
+        let mut removed = Vec::new();
+        {
+            let mut listeners = listeners.lock().unwrap();
+            listeners.listeners.retain(|(id, _)| *id != self.id);
+            for subscriptions in listeners.subscriptions.values_mut() {
+                let (matching, kept) = std::mem::take(subscriptions).into_iter().partition(|(id, _)| *id == self.id);
+                *subscriptions = kept;
+                removed.extend::<Vec<(u64, Subscription)>>(matching);
+            }
+        }
+        drop(removed);
+    }
+}
+
+// [markdown]
+// `TapManager` drives several Tap devices at once, e.g. a left and a right
+// Tap Strap. Every device keeps its own `Client`, so modes and haptics are
+// set per device, and listeners can tell devices apart by `EventSource`.
+// Listeners are also attached to devices added after registration, and
+// stay registered until their `ManagerSubscription` is dropped,
+// `clear_listeners` is called or the device is removed.
+// Listeners a device's model does not support are skipped on that device.
+#[derive(Debug, Default)]
+pub struct TapManager {
+    devices: BTreeMap<DeviceId, Client>,
+    listeners: Arc<StdMutex<ManagerListeners>>,
+}
+
+impl TapManager {
+    pub fn new() -> Self {
+        TapManager::default()
+    }
+
+    pub async fn connect(&mut self, address: BDAddr) -> Result<DeviceId, TapError> {
+        let mut client = Client::new(Some(address)).await?;
+        client.run().await?;
+        self.add(client).await
+    }
+
+    pub async fn connect_all(&mut self) -> Result<Vec<DeviceId>, TapError> {
+        let mut device_ids = Vec::new();
+        for tap in discover(&DiscoveryOptions::default()).await? {
+            if tap.connected && !self.devices.contains_key(&DeviceId::from(tap.address)) {
+                device_ids.push(self.connect(tap.address).await?);
+            }
+        }
+        Ok(device_ids)
+    }
+
+    // The client must already be running.
+    pub async fn add(&mut self, client: Client) -> Result<DeviceId, TapError> {
+        let device_id = client.device_id().ok_or(TapError::NotConnected)?;
+        let listeners = self.listeners.lock().unwrap().listeners.clone();
+        let mut subscriptions = Vec::new();
+        for (id, listener) in listeners {
+            if let Some(subscription) = subscribe_listener(&client, listener).await? {
+                subscriptions.push((id, subscription));
+            }
+        }
+        self.devices.insert(device_id, client);
+        self.listeners.lock().unwrap().subscriptions.insert(device_id, subscriptions);
+        Ok(device_id)
+    }
+
+    pub fn remove(&mut self, device_id: DeviceId) -> Option<Client> {
+        let subscriptions = self.listeners.lock().unwrap().subscriptions.remove(&device_id);
+        drop(subscriptions);
+        self.devices.remove(&device_id)
+    }
+
+    // Removes every listener from every device.
+    pub fn clear_listeners(&mut self) {
+        let subscriptions = {
+            let mut listeners = self.listeners.lock().unwrap();
+            listeners.listeners.clear();
+            std::mem::take(&mut listeners.subscriptions)
+        };
+        drop(subscriptions);
+    }
+
+    pub fn device_ids(&self) -> Vec<DeviceId> {
+        self.devices.keys().copied().collect()
+    }
+
+    pub fn device(&self, device_id: DeviceId) -> Option<&Client> {
+        self.devices.get(&device_id)
+    }
+
+    pub fn device_mut(&mut self, device_id: DeviceId) -> Option<&mut Client> {
+        self.devices.get_mut(&device_id)
+    }
+
+    pub async fn set_input_mode(&mut self, device_id: DeviceId, mode: InputMode, sensitivity: Option<Vec<u8>>) -> Result<(), TapError> {
+        self.device_mut_or_err(device_id)?.set_input_mode(mode, sensitivity).await
+    }
+
+    pub async fn set_input_type(&mut self, device_id: DeviceId, input_type: InputType) -> Result<(), TapError> {
+        self.device_mut_or_err(device_id)?.set_input_type(input_type).await
+    }
+
+    pub async fn set_mouse_mode(&mut self, device_id: DeviceId, mouse_mode: MouseModes) -> Result<(), TapError> {
+        self.device_mut_or_err(device_id)?.set_mouse_mode(mouse_mode).await
+    }
+
+    pub async fn send_vibration_sequence(&mut self, device_id: DeviceId, sequence: Vec<u16>) -> Result<(), TapError> {
+        self.device_mut_or_err(device_id)?.send_vibration_sequence(sequence).await
+    }
+
+    fn device_mut_or_err(&mut self, device_id: DeviceId) -> Result<&mut Client, TapError> {
+        self.devices
+            .get_mut(&device_id)
+            .ok_or(TapError::DeviceNotFound)
+    }
+
+    pub async fn register_air_gesture_events(&mut self, listener: impl Fn(EventSource, AirGestures) + Send + Sync + 'static) -> Result<ManagerSubscription, TapError> {
+        self.register(ManagerListener::AirGesture(Arc::new(listener))).await
+    }
+    pub async fn register_tap_events(&mut self, listener: impl Fn(EventSource, TapCode) + Send + Sync + 'static) -> Result<ManagerSubscription, TapError> {
+        self.register(ManagerListener::Tap(Arc::new(listener))).await
+    }
+    pub async fn register_raw_data_events(&mut self, listener: impl Fn(EventSource, Vec<u8>) + Send + Sync + 'static) -> Result<ManagerSubscription, TapError> {
+        self.register(ManagerListener::RawData(Arc::new(listener))).await
+    }
+    pub async fn register_raw_sensor_events(&mut self, listener: impl Fn(EventSource, RawSensorPacket) + Send + Sync + 'static) -> Result<ManagerSubscription, TapError> {
+        self.register(ManagerListener::RawSensor(Arc::new(listener))).await
+    }
+    pub async fn register_mouse_events(&mut self, listener: impl Fn(EventSource, i16, i16, bool) + Send + Sync + 'static) -> Result<ManagerSubscription, TapError> {
+        self.register(ManagerListener::Mouse(Arc::new(listener))).await
+    }
+    pub async fn register_air_gesture_state_events(&mut self, listener: impl Fn(EventSource, bool) + Send + Sync + 'static) -> Result<ManagerSubscription, TapError> {
+        self.register(ManagerListener::AirGestureState(Arc::new(listener))).await
+    }
+    pub async fn register_connection_events(&mut self, listener: impl Fn(EventSource) + Send + Sync + 'static) -> Result<ManagerSubscription, TapError> {
+        self.register(ManagerListener::Connection(Arc::new(listener))).await
+    }
+    pub async fn register_disconnection_events(&mut self, listener: impl Fn(EventSource) + Send + Sync + 'static) -> Result<ManagerSubscription, TapError> {
+        self.register(ManagerListener::Disconnection(Arc::new(listener))).await
+    }
+
+    // When subscribing one of the devices fails, the listener is removed from all of them.
+    async fn register(&mut self, listener: ManagerListener) -> Result<ManagerSubscription, TapError> {
+        let id = {
+            let mut listeners = self.listeners.lock().unwrap();
+            let id = listeners.next_id;
+            listeners.next_id += 1;
+            listeners.listeners.push((id, listener.clone()));
+            id
+        };
+        let handle = ManagerSubscription {
+            listeners: Arc::downgrade(&self.listeners),
+            id,
+        };
+
+        for (device_id, client) in &self.devices {
+            if let Some(subscription) = subscribe_listener(client, listener.clone()).await? {
+                self.listeners.lock().unwrap().subscriptions.entry(*device_id).or_default().push((id, subscription));
+            }
+        }
+        Ok(handle)
+    }
+}
+
+// `None` when the model of the device does not support the listener,
+// e.g. air gestures on a Tap Strap next to a Tap Strap 2.
+async fn subscribe_listener(client: &Client, listener: ManagerListener) -> Result<Option<Subscription>, TapError> {
+    let subscription = match listener {
+        ManagerListener::AirGesture(listener) => client.register_air_gesture_events(move |source, gesture| listener(source, gesture)).await,
+        ManagerListener::Tap(listener) => client.register_tap_events(move |source, tap_code| listener(source, tap_code)).await,
+        ManagerListener::RawData(listener) => client.register_raw_data_events(move |source, data| listener(source, data)).await,
+        ManagerListener::RawSensor(listener) => client.register_raw_sensor_events(move |source, packet| listener(source, packet)).await,
+        ManagerListener::Mouse(listener) => client.register_mouse_events(move |source, vx, vy, proximity| listener(source, vx, vy, proximity)).await,
+        ManagerListener::AirGestureState(listener) => client.register_air_gesture_state_events(move |source, state| listener(source, state)).await,
+        ManagerListener::Connection(listener) => client.register_connection_events(move |source| listener(source)).await,
+        ManagerListener::Disconnection(listener) => client.register_disconnection_events(move |source| listener(source)).await,
+    };
+    match subscription {
+        Ok(subscription) => Ok(Some(subscription)),
+        Err(TapError::Unsupported { .. }) => Ok(None),
+        Err(e) => Err(e),
+    }
+}
@@ -0,0 +1,305 @@
+use std::collections::HashMap;
+
+use crate::types::tap_code::TapCode;
+
+const LETTERS: [(&str, char); 26] = [
+    ("10000", 'a'),
+    ("01001", 'b'),
+    ("01110", 'c'),
+    ("11100", 'd'),
+    ("01000", 'e'),
+    ("11010", 'f'),
+    ("00111", 'g'),
+    ("10011", 'h'),
+    ("00100", 'i'),
+    ("01011", 'j'),
+    ("01101", 'k'),
+    ("00110", 'l'),
+    ("10110", 'm'),
+    ("11000", 'n'),
+    ("00010", 'o'),
+    ("11110", 'p'),
+    ("10111", 'q'),
+    ("10010", 'r'),
+    ("00011", 's'),
+    ("01100", 't'),
+    ("00001", 'u'),
+    ("10101", 'v'),
+    ("01010", 'w'),
+    ("11011", 'x'),
+    ("10001", 'y'),
+    ("11001", 'z'),
+];
+
+const NUMBERS: [(&str, char); 10] = [
+    ("10000", '1'),
+    ("01000", '2'),
+    ("00100", '3'),
+    ("00010", '4'),
+    ("00001", '5'),
+    ("11000", '6'),
+    ("01100", '7'),
+    ("00110", '8'),
+    ("00011", '9'),
+    ("10001", '0'),
+];
+
+const SYMBOLS: [(&str, char); 20] = [
+    ("10000", '.'),
+    ("01000", ','),
+    ("00100", '?'),
+    ("00010", '!'),
+    ("00001", '\''),
+    ("11000", '-'),
+    ("01100", '('),
+    ("00110", ')'),
+    ("00011", ':'),
+    ("10001", ';'),
+    ("10010", '"'),
+    ("01001", '@'),
+    ("01010", '/'),
+    ("11010", '+'),
+    ("11100", '='),
+    ("01110", '*'),
+    ("00111", '_'),
+    ("10011", '#'),
+    ("11001", '&'),
+    ("01011", '$'),
+];
+
+const SPACE: &str = "11111";
+const BACKSPACE: &str = "01111";
+const ENTER: &str = "11101";
+const SHIFT: &str = "10100";
+const NUMBERS_TOGGLE: &str = "00101";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TapMapLayer {
+    Letters,
+    Numbers,
+    Symbols,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TapMapOutput {
+    Char(char),
+    Backspace,
+    Enter,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TapMapAction {
+    Output(TapMapOutput),
+    Shift,
+    NumbersToggle,
+}
+
+// [markdown]
+// `TapAlphabet` maps tap codes to actions, one table per layer.
+// `TapAlphabet::standard()` is the English Tap alphabet: letters,
+// numbers and symbols layers, with space, backspace, enter, shift
+// and the numbers switch available on every layer.
+#[derive(Debug, Clone)]
+pub struct TapAlphabet {
+    actions: HashMap<(TapMapLayer, TapCode), TapMapAction>,
+}
+
+impl TapAlphabet {
+    pub fn empty() -> Self {
+        TapAlphabet { actions: HashMap::new() }
+    }
+
+    pub fn standard() -> Self {
+        let mut alphabet = TapAlphabet::empty();
+        let layers = [
+            (TapMapLayer::Letters, &LETTERS[..]),
+            (TapMapLayer::Numbers, &NUMBERS[..]),
+            (TapMapLayer::Symbols, &SYMBOLS[..]),
+        ];
+
+        for (layer, characters) in layers {
+            for (code, character) in characters {
+                alphabet.set(layer, tap_code(code), TapMapAction::Output(TapMapOutput::Char(*character)));
+            }
+            alphabet.set(layer, tap_code(SPACE), TapMapAction::Output(TapMapOutput::Char(' ')));
+            alphabet.set(layer, tap_code(BACKSPACE), TapMapAction::Output(TapMapOutput::Backspace));
+            alphabet.set(layer, tap_code(ENTER), TapMapAction::Output(TapMapOutput::Enter));
+            alphabet.set(layer, tap_code(SHIFT), TapMapAction::Shift);
+            alphabet.set(layer, tap_code(NUMBERS_TOGGLE), TapMapAction::NumbersToggle);
+        }
+
+        alphabet
+    }
+
+    pub fn set(&mut self, layer: TapMapLayer, tap_code: TapCode, action: TapMapAction) {
+        self.actions.insert((layer, tap_code), action);
+    }
+
+    pub fn lookup(&self, layer: TapMapLayer, tap_code: TapCode) -> Option<TapMapAction> {
+        self.actions.get(&(layer, tap_code)).copied()
+    }
+}
+
+impl Default for TapAlphabet {
+    fn default() -> Self {
+        TapAlphabet::standard()
+    }
+}
+
+fn tap_code(code: &str) -> TapCode {
+    code.parse().expect("Tap alphabet codes are valid")
+}
+
+// [markdown]
+// `TapMapDecoder` turns a stream of tap codes into text.
+// Shift applies to the next letter, two shifts in a row toggle caps lock.
+// The numbers switch moves between the letters and numbers layers, and
+// shift on the numbers layer moves to the symbols layer and back.
+#[derive(Debug, Clone)]
+pub struct TapMapDecoder {
+    alphabet: TapAlphabet,
+    layer: TapMapLayer,
+    shift: bool,
+    caps_lock: bool,
+}
+
+impl TapMapDecoder {
+    pub fn new(alphabet: TapAlphabet) -> Self {
+        TapMapDecoder {
+            alphabet,
+            layer: TapMapLayer::Letters,
+            shift: false,
+            caps_lock: false,
+        }
+    }
+
+    pub fn layer(&self) -> TapMapLayer {
+        self.layer
+    }
+
+    pub fn is_shifted(&self) -> bool {
+        self.shift
+    }
+
+    pub fn is_caps_lock(&self) -> bool {
+        self.caps_lock
+    }
+
+    pub fn reset(&mut self) {
+        self.layer = TapMapLayer::Letters;
+        self.shift = false;
+        self.caps_lock = false;
+    }
+
+    pub fn decode(&mut self, tap_code: impl Into<TapCode>) -> Option<TapMapOutput> {
+        let action = self.alphabet.lookup(self.layer, tap_code.into())?;
+
+        match action {
+            TapMapAction::Shift => {
+                match self.layer {
+                    TapMapLayer::Letters if self.caps_lock => self.caps_lock = false,
+                    TapMapLayer::Letters if self.shift => {
+                        self.shift = false;
+                        self.caps_lock = true;
+                    }
+                    TapMapLayer::Letters => self.shift = true,
+                    TapMapLayer::Numbers => self.layer = TapMapLayer::Symbols,
+                    TapMapLayer::Symbols => self.layer = TapMapLayer::Numbers,
+                }
+                None
+            }
+            TapMapAction::NumbersToggle => {
+                self.layer = match self.layer {
+                    TapMapLayer::Letters => TapMapLayer::Numbers,
+                    TapMapLayer::Numbers | TapMapLayer::Symbols => TapMapLayer::Letters,
+                };
+                self.shift = false;
+                None
+            }
+            TapMapAction::Output(TapMapOutput::Char(character)) if self.layer == TapMapLayer::Letters => {
+                let upper_case = self.shift || self.caps_lock;
+                self.shift = false;
+                if upper_case {
+                    Some(TapMapOutput::Char(character.to_ascii_uppercase()))
+                } else {
+                    Some(TapMapOutput::Char(character))
+                }
+            }
+            TapMapAction::Output(output) => {
+                self.shift = false;
+                Some(output)
+            }
+        }
+    }
+}
+
+impl Default for TapMapDecoder {
+    fn default() -> Self {
+        TapMapDecoder::new(TapAlphabet::standard())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn type_codes(decoder: &mut TapMapDecoder, codes: &[&str]) -> String {
+        codes
+            .iter()
+            .filter_map(|code| match decoder.decode(tap_code(code)) {
+                Some(TapMapOutput::Char(character)) => Some(character),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn shift_applies_to_the_next_letter() {
+        let mut decoder = TapMapDecoder::default();
+        assert_eq!(type_codes(&mut decoder, &[SHIFT, "10000", "10000"]), "Aa");
+        assert!(!decoder.is_shifted());
+    }
+
+    #[test]
+    fn double_shift_toggles_caps_lock() {
+        let mut decoder = TapMapDecoder::default();
+        assert_eq!(type_codes(&mut decoder, &[SHIFT, SHIFT, "10000", "01001"]), "AB");
+        assert!(decoder.is_caps_lock());
+
+        assert_eq!(type_codes(&mut decoder, &[SHIFT, "10000"]), "a");
+        assert!(!decoder.is_caps_lock());
+    }
+
+    #[test]
+    fn switches_between_layers() {
+        let mut decoder = TapMapDecoder::default();
+        assert_eq!(type_codes(&mut decoder, &[NUMBERS_TOGGLE, "10000"]), "1");
+        assert_eq!(decoder.layer(), TapMapLayer::Numbers);
+
+        assert_eq!(type_codes(&mut decoder, &[SHIFT, "10000"]), ".");
+        assert_eq!(decoder.layer(), TapMapLayer::Symbols);
+
+        assert_eq!(type_codes(&mut decoder, &[SHIFT, "10000"]), "1");
+        assert_eq!(type_codes(&mut decoder, &[NUMBERS_TOGGLE, "10000"]), "a");
+        assert_eq!(decoder.layer(), TapMapLayer::Letters);
+    }
+
+    #[test]
+    fn shared_keys_work_on_every_layer() {
+        let mut decoder = TapMapDecoder::default();
+        for toggle in [false, true] {
+            if toggle {
+                decoder.decode(tap_code(NUMBERS_TOGGLE));
+            }
+            assert_eq!(decoder.decode(tap_code(SPACE)), Some(TapMapOutput::Char(' ')));
+            assert_eq!(decoder.decode(tap_code(BACKSPACE)), Some(TapMapOutput::Backspace));
+            assert_eq!(decoder.decode(tap_code(ENTER)), Some(TapMapOutput::Enter));
+        }
+    }
+
+    #[test]
+    fn unmapped_codes_decode_to_nothing() {
+        let mut decoder = TapMapDecoder::new(TapAlphabet::empty());
+        assert_eq!(decoder.decode(tap_code("10000")), None);
+    }
+}
@@ -0,0 +1,144 @@
+use async_trait::async_trait;
+use btleplug::api::{BDAddr, Central, CentralEvent, Characteristic as GattCharacteristic, Peripheral, ScanFilter, WriteType};
+use btleplug::platform::{Adapter, Peripheral as PlatformPeripheral};
+use futures::StreamExt;
+use std::collections::BTreeSet;
+use std::sync::RwLock;
+use std::time::Duration;
+
+use super::{ConnectionStream, NotificationStream, TapTransport};
+use crate::error::TapError;
+use crate::types::device::ConnectionState;
+
+// How long a reconnect attempt scans for a device the adapter has not seen.
+const RECONNECT_SCAN_TIME: Duration = Duration::from_secs(2);
+
+// [markdown]
+// `TapTransport` backed by a btleplug peripheral.
+// The peripheral is replaced when the transport reconnects,
+// because it has to be found again on the adapter by its address.
+#[derive(Debug)]
+pub struct BleTransport {
+    adapter: Adapter,
+    address: BDAddr,
+    peripheral: RwLock<PlatformPeripheral>,
+}
+
+impl BleTransport {
+    pub fn new(adapter: Adapter, peripheral: PlatformPeripheral) -> Self {
+        BleTransport {
+            adapter,
+            address: peripheral.address(),
+            peripheral: RwLock::new(peripheral),
+        }
+    }
+
+    pub fn adapter(&self) -> &Adapter {
+        &self.adapter
+    }
+
+    pub fn peripheral(&self) -> PlatformPeripheral {
+        self.peripheral.read().unwrap().clone()
+    }
+
+    async fn find_peripheral(&self) -> Result<Option<PlatformPeripheral>, TapError> {
+        Ok(self
+            .adapter
+            .peripherals()
+            .await?
+            .into_iter()
+            .find(|peripheral| peripheral.address() == self.address))
+    }
+}
+
+#[async_trait]
+impl TapTransport for BleTransport {
+    fn address(&self) -> BDAddr {
+        self.address
+    }
+
+    async fn name(&self) -> Option<String> {
+        self.peripheral().properties().await.ok()??.local_name
+    }
+
+    async fn connect(&self) -> Result<(), TapError> {
+        let peripheral = self.peripheral();
+        if !peripheral.is_connected().await? {
+            peripheral.connect().await?;
+        }
+        peripheral.discover_services().await?;
+        Ok(())
+    }
+
+    async fn reconnect(&self) -> Result<(), TapError> {
+        let peripheral = match self.find_peripheral().await? {
+            Some(peripheral) => peripheral,
+            None => {
+                // Scan only while looking for the peripheral, found or not.
+                self.adapter.start_scan(ScanFilter::default()).await?;
+                tokio::time::sleep(RECONNECT_SCAN_TIME).await;
+                let found = self.find_peripheral().await;
+                self.adapter.stop_scan().await?;
+                found?.ok_or(TapError::DeviceNotFound)?
+            }
+        };
+
+        if !peripheral.is_connected().await? {
+            peripheral.connect().await?;
+        }
+        peripheral.discover_services().await?;
+        *self.peripheral.write().unwrap() = peripheral;
+        Ok(())
+    }
+
+    async fn disconnect(&self) -> Result<(), TapError> {
+        self.peripheral().disconnect().await?;
+        Ok(())
+    }
+
+    async fn is_connected(&self) -> Result<bool, TapError> {
+        Ok(self.peripheral().is_connected().await?)
+    }
+
+    fn characteristics(&self) -> BTreeSet<GattCharacteristic> {
+        self.peripheral().characteristics()
+    }
+
+    async fn write(&self, characteristic: &GattCharacteristic, data: &[u8]) -> Result<(), TapError> {
+        self.peripheral().write(characteristic, data, WriteType::WithResponse).await?;
+        Ok(())
+    }
+
+    async fn read(&self, characteristic: &GattCharacteristic) -> Result<Vec<u8>, TapError> {
+        Ok(self.peripheral().read(characteristic).await?)
+    }
+
+    async fn subscribe(&self, characteristic: &GattCharacteristic) -> Result<(), TapError> {
+        self.peripheral().subscribe(characteristic).await?;
+        Ok(())
+    }
+
+    async fn unsubscribe(&self, characteristic: &GattCharacteristic) -> Result<(), TapError> {
+        self.peripheral().unsubscribe(characteristic).await?;
+        Ok(())
+    }
+
+    async fn notifications(&self) -> Result<NotificationStream, TapError> {
+        Ok(self.peripheral().notifications().await?)
+    }
+
+    // Connection changes come from the adapter's central event stream,
+    // a device going out of range is reported as a disconnection.
+    async fn connection_events(&self) -> Result<ConnectionStream, TapError> {
+        let peripheral_id = self.peripheral().id();
+        let events = self.adapter.events().await?;
+        Ok(Box::pin(events.filter_map(move |event| {
+            let state = match event {
+                CentralEvent::DeviceConnected(id) if id == peripheral_id => Some(ConnectionState::Connected),
+                CentralEvent::DeviceDisconnected(id) if id == peripheral_id => Some(ConnectionState::Disconnected),
+                _ => None,
+            };
+            futures::future::ready(state)
+        })))
+    }
+}
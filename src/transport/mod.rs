@@ -0,0 +1,64 @@
+use async_trait::async_trait;
+use btleplug::api::{BDAddr, Characteristic as GattCharacteristic, ValueNotification};
+use futures::stream::Stream;
+use std::collections::BTreeSet;
+use std::fmt::Debug;
+use std::pin::Pin;
+use uuid::Uuid;
+
+use crate::error::TapError;
+use crate::types::device::ConnectionState;
+
+mod ble;
+pub use self::ble::BleTransport;
+
+mod simulated;
+pub use self::simulated::SimulatedTap;
+
+mod replay;
+pub use self::replay::{ReplaySpeed, ReplayTransport};
+
+pub type NotificationStream = Pin<Box<dyn Stream<Item = ValueNotification> + Send>>;
+pub type ConnectionStream = Pin<Box<dyn Stream<Item = ConnectionState> + Send>>;
+
+// [markdown]
+// `TapTransport` is the link between the SDK and a Tap device.
+// `Client` and `EventManager` only talk to the device through it,
+// so the btleplug backend can be swapped for another one.
+#[async_trait]
+pub trait TapTransport: Debug + Send + Sync {
+    fn address(&self) -> BDAddr;
+
+    async fn name(&self) -> Option<String>;
+
+    async fn connect(&self) -> Result<(), TapError>;
+
+    async fn disconnect(&self) -> Result<(), TapError>;
+
+    // Brings a dropped link back up, including service discovery.
+    async fn reconnect(&self) -> Result<(), TapError> {
+        self.connect().await
+    }
+
+    async fn is_connected(&self) -> Result<bool, TapError>;
+
+    fn characteristics(&self) -> BTreeSet<GattCharacteristic>;
+
+    async fn write(&self, characteristic: &GattCharacteristic, data: &[u8]) -> Result<(), TapError>;
+
+    async fn read(&self, characteristic: &GattCharacteristic) -> Result<Vec<u8>, TapError>;
+
+    async fn subscribe(&self, characteristic: &GattCharacteristic) -> Result<(), TapError>;
+
+    async fn unsubscribe(&self, characteristic: &GattCharacteristic) -> Result<(), TapError>;
+
+    async fn notifications(&self) -> Result<NotificationStream, TapError>;
+
+    async fn connection_events(&self) -> Result<ConnectionStream, TapError>;
+
+    fn find_characteristic(&self, uuid: Uuid) -> Option<GattCharacteristic> {
+        self.characteristics()
+            .into_iter()
+            .find(|characteristic| characteristic.uuid == uuid)
+    }
+}
@@ -0,0 +1,287 @@
+use async_trait::async_trait;
+use btleplug::api::{BDAddr, CharPropFlags, Characteristic as GattCharacteristic, ValueNotification};
+use futures::channel::mpsc::{self as unbounded, UnboundedSender};
+use std::collections::BTreeSet;
+use std::path::Path;
+use std::str::FromStr;
+use std::sync::Mutex;
+use std::time::Duration;
+use strum::IntoEnumIterator;
+use tokio::sync::mpsc::{self, Sender};
+use uuid::Uuid;
+
+use super::{ConnectionStream, NotificationStream, TapTransport};
+use crate::error::TapError;
+use crate::recorder::{Record, RecordKind, RecordingHeader, RECORDING_FORMAT, RECORDING_VERSION};
+use crate::types::device::ConnectionState;
+use crate::types::uuid::characteristics::Characteristic;
+
+// How many packets the dispatcher may lag behind the replay.
+const REPLAY_BUFFER: usize = 64;
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum ReplaySpeed {
+    // The gaps between packets as they were recorded.
+    #[default]
+    RealTime,
+    // 2.0 plays twice as fast, 0.5 at half speed.
+    Scaled(f64),
+    AsFastAsPossible,
+}
+
+impl ReplaySpeed {
+    fn delay(&self, gap: Duration) -> Option<Duration> {
+        match self {
+            ReplaySpeed::RealTime => Some(gap),
+            ReplaySpeed::Scaled(factor) if *factor > 0.0 => Some(gap.div_f64(*factor)),
+            ReplaySpeed::Scaled(_) | ReplaySpeed::AsFastAsPossible => None,
+        }
+    }
+}
+
+// [markdown]
+// `ReplayTransport` plays a session written by `SessionRecorder` back as if
+// it came from the device, so the recorded notifications go through the
+// same dispatcher, decoders and listeners. Like a real device, only
+// subscribed characteristics are delivered, and commands written during
+// the replay are accepted and dropped. Playback runs when `play` is called,
+// once the listeners are registered.
+#[derive(Debug)]
+pub struct ReplayTransport {
+    header: RecordingHeader,
+    address: BDAddr,
+    notifications: Vec<Record>,
+    speed: ReplaySpeed,
+    state: Mutex<ReplayState>,
+}
+
+#[derive(Debug, Default)]
+struct ReplayState {
+    connected: bool,
+    subscribed: BTreeSet<Uuid>,
+    listeners: Vec<Sender<ValueNotification>>,
+    connection_listeners: Vec<UnboundedSender<ConnectionState>>,
+}
+
+impl ReplayState {
+    fn set_connection_state(&mut self, connection_state: ConnectionState) {
+        self.connected = connection_state == ConnectionState::Connected;
+        self.connection_listeners
+            .retain(|listener| listener.unbounded_send(connection_state).is_ok());
+    }
+}
+
+impl ReplayTransport {
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, TapError> {
+        let source = std::fs::read_to_string(path)?;
+        let mut lines = source.lines().filter(|line| !line.trim().is_empty());
+
+        let header: RecordingHeader = match lines.next() {
+            Some(line) => serde_json::from_str(line).map_err(|e| TapError::Protocol(format!("Invalid recording header: {}", e)))?,
+            None => return Err(TapError::Protocol(String::from("Empty recording"))),
+        };
+        if header.format != RECORDING_FORMAT || header.version > RECORDING_VERSION {
+            return Err(TapError::Protocol(format!("Unsupported recording format {} version {}", header.format, header.version)));
+        }
+        let address = BDAddr::from_str(&header.device).map_err(|e| TapError::Protocol(format!("Invalid device address {:?}: {}", header.device, e)))?;
+
+        let mut notifications = Vec::new();
+        for (index, line) in lines.enumerate() {
+            let record: Record = serde_json::from_str(line)
+                .map_err(|e| TapError::Protocol(format!("Invalid record {} of the recording: {}", index + 1, e)))?;
+            if record.kind == RecordKind::Notification {
+                notifications.push(record);
+            }
+        }
+
+        Ok(ReplayTransport {
+            header,
+            address,
+            notifications,
+            speed: ReplaySpeed::default(),
+            state: Mutex::default(),
+        })
+    }
+
+    pub fn with_speed(mut self, speed: ReplaySpeed) -> Self {
+        self.speed = speed;
+        self
+    }
+
+    pub fn header(&self) -> &RecordingHeader {
+        &self.header
+    }
+
+    // Delivers the recorded notifications at the selected speed and returns
+    // once the dispatcher has taken the last one.
+    pub async fn play(&self) -> Result<(), TapError> {
+        let mut previous = None;
+        for record in &self.notifications {
+            if let Some(delay) = previous.and_then(|previous| self.speed.delay(Duration::from_micros(record.timestamp.saturating_sub(previous)))) {
+                tokio::time::sleep(delay).await;
+            }
+            previous = Some(record.timestamp);
+
+            let uuid = Uuid::from_u128(record.characteristic.as_uuid());
+            let listeners = {
+                let state = self.state.lock().unwrap();
+                if !state.connected {
+                    return Err(TapError::NotConnected);
+                }
+                if !state.subscribed.contains(&uuid) {
+                    continue;
+                }
+                state.listeners.clone()
+            };
+
+            let notification = ValueNotification { uuid, value: record.data.clone() };
+            for listener in listeners {
+                // A closed stream is dropped by the next `notifications` call.
+                let _ = listener.send(notification.clone()).await;
+            }
+        }
+
+        loop {
+            let drained = {
+                let mut state = self.state.lock().unwrap();
+                state.listeners.retain(|listener| !listener.is_closed());
+                state.listeners.iter().all(|listener| listener.capacity() == listener.max_capacity())
+            };
+            if drained {
+                return Ok(());
+            }
+            tokio::time::sleep(Duration::from_millis(1)).await;
+        }
+    }
+}
+
+#[async_trait]
+impl TapTransport for ReplayTransport {
+    fn address(&self) -> BDAddr {
+        self.address
+    }
+
+    async fn name(&self) -> Option<String> {
+        self.header.name.clone()
+    }
+
+    async fn connect(&self) -> Result<(), TapError> {
+        self.state.lock().unwrap().set_connection_state(ConnectionState::Connected);
+        Ok(())
+    }
+
+    async fn disconnect(&self) -> Result<(), TapError> {
+        let mut state = self.state.lock().unwrap();
+        state.subscribed.clear();
+        state.listeners.clear();
+        state.set_connection_state(ConnectionState::Disconnected);
+        Ok(())
+    }
+
+    async fn is_connected(&self) -> Result<bool, TapError> {
+        Ok(self.state.lock().unwrap().connected)
+    }
+
+    // Device information is not recorded.
+    fn characteristics(&self) -> BTreeSet<GattCharacteristic> {
+        Characteristic::iter()
+            .filter(|characteristic| characteristic.properties().intersects(CharPropFlags::NOTIFY | CharPropFlags::WRITE))
+            .map(|characteristic| GattCharacteristic {
+                uuid: Uuid::from_u128(characteristic.as_uuid()),
+                service_uuid: Uuid::from_u128(characteristic.service().as_uuid()),
+                properties: characteristic.properties(),
+                descriptors: BTreeSet::new(),
+            })
+            .collect()
+    }
+
+    async fn write(&self, _characteristic: &GattCharacteristic, _data: &[u8]) -> Result<(), TapError> {
+        if !self.state.lock().unwrap().connected {
+            return Err(TapError::NotConnected);
+        }
+        Ok(())
+    }
+
+    async fn read(&self, characteristic: &GattCharacteristic) -> Result<Vec<u8>, TapError> {
+        Err(TapError::Protocol(format!("Characteristic {:?} can't be read from a recording", characteristic.uuid)))
+    }
+
+    async fn subscribe(&self, characteristic: &GattCharacteristic) -> Result<(), TapError> {
+        let mut state = self.state.lock().unwrap();
+        if !state.connected {
+            return Err(TapError::NotConnected);
+        }
+        state.subscribed.insert(characteristic.uuid);
+        Ok(())
+    }
+
+    async fn unsubscribe(&self, characteristic: &GattCharacteristic) -> Result<(), TapError> {
+        self.state.lock().unwrap().subscribed.remove(&characteristic.uuid);
+        Ok(())
+    }
+
+    async fn notifications(&self) -> Result<NotificationStream, TapError> {
+        let (sender, receiver) = mpsc::channel(REPLAY_BUFFER);
+        let mut state = self.state.lock().unwrap();
+        state.listeners.retain(|listener| !listener.is_closed());
+        state.listeners.push(sender);
+        Ok(Box::pin(futures::stream::unfold(receiver, |mut receiver| async move {
+            receiver.recv().await.map(|notification| (notification, receiver))
+        })))
+    }
+
+    async fn connection_events(&self) -> Result<ConnectionStream, TapError> {
+        let (sender, receiver) = unbounded::unbounded();
+        self.state.lock().unwrap().connection_listeners.push(sender);
+        Ok(Box::pin(receiver))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transport::SimulatedTap;
+    use crate::{Client, ClientBuilder};
+    use std::sync::Arc;
+
+    #[tokio::test]
+    async fn replays_a_recorded_session() {
+        let path = std::env::temp_dir().join(format!("tap-sdk-replay-{}.jsonl", std::process::id()));
+        let tap_codes = [0b00001, 0b01001, 0b11111];
+
+        let simulated_tap = Arc::new(SimulatedTap::new());
+        let mut tap_client = ClientBuilder::new()
+            .transport(simulated_tap.clone())
+            .auto_refresh_interval(None)
+            .record_to(&path)
+            .connect()
+            .await
+            .unwrap();
+        let subscription = tap_client.register_tap_events(|_, _| {}).await.unwrap();
+        for tap_code in tap_codes {
+            simulated_tap.push_tap(tap_code);
+        }
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        tap_client.stop_recording().await;
+        drop(subscription);
+        drop(tap_client);
+
+        let replay = ReplayTransport::open(&path).unwrap();
+        assert_eq!(replay.header().name.as_deref(), Some("Tap Simulated"));
+        assert_eq!(replay.address(), simulated_tap.address());
+
+        let mut replay_client = Client::from_recording(&path, ReplaySpeed::AsFastAsPossible).unwrap();
+        replay_client.run().await.unwrap();
+        let received = Arc::new(Mutex::new(Vec::new()));
+        let taps = Arc::clone(&received);
+        let _subscription = replay_client
+            .register_tap_events(move |_, tap_code| taps.lock().unwrap().push(tap_code.as_u8()))
+            .await
+            .unwrap();
+        replay_client.replay().await.unwrap();
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(*received.lock().unwrap(), tap_codes);
+    }
+}
@@ -0,0 +1,310 @@
+use async_trait::async_trait;
+use btleplug::api::{BDAddr, CharPropFlags, Characteristic as GattCharacteristic, ValueNotification};
+use futures::channel::mpsc::{self, UnboundedSender};
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::sync::Mutex;
+use strum::IntoEnumIterator;
+use uuid::Uuid;
+
+use super::{ConnectionStream, NotificationStream, TapTransport};
+use crate::error::TapError;
+use crate::types::air_gestures::AirGestures;
+use crate::types::device::ConnectionState;
+use crate::types::model::TapModel;
+use crate::types::uuid::characteristics::Characteristic;
+use crate::types::uuid::services::Service;
+
+// [markdown]
+// `SimulatedTap` is an in-memory Tap device. It records every command
+// written to `TapMode` and `UiCmd`, and lets a test script push data
+// into the notification streams, so no hardware or Bluetooth is needed.
+// Battery level and device information are served from settable values.
+#[derive(Debug)]
+pub struct SimulatedTap {
+    address: BDAddr,
+    name: String,
+    state: Mutex<SimulatedState>,
+}
+
+#[derive(Debug, Default)]
+struct SimulatedState {
+    connected: bool,
+    out_of_range: bool,
+    subscribed: HashSet<Uuid>,
+    written: Vec<(Characteristic, Vec<u8>)>,
+    values: HashMap<Characteristic, Vec<u8>>,
+    // Characteristics the device does not expose.
+    missing: HashSet<Characteristic>,
+    listeners: Vec<UnboundedSender<ValueNotification>>,
+    connection_listeners: Vec<UnboundedSender<ConnectionState>>,
+}
+
+impl SimulatedState {
+    fn drop_link(&mut self) {
+        self.subscribed.clear();
+        self.listeners.clear();
+        self.set_connection_state(ConnectionState::Disconnected);
+    }
+
+    fn set_connection_state(&mut self, connection_state: ConnectionState) {
+        self.connected = connection_state == ConnectionState::Connected;
+        self.connection_listeners
+            .retain(|listener| listener.unbounded_send(connection_state).is_ok());
+    }
+}
+
+impl Default for SimulatedTap {
+    fn default() -> Self {
+        SimulatedTap::new()
+    }
+}
+
+impl SimulatedTap {
+    pub fn new() -> Self {
+        SimulatedTap::with_address(BDAddr::default())
+    }
+
+    pub fn with_address(address: BDAddr) -> Self {
+        let values = HashMap::from([
+            (Characteristic::BatteryLevel, vec![100]),
+            (Characteristic::ManufacturerName, b"Tap Systems Inc.".to_vec()),
+            (Characteristic::ModelNumber, b"Simulated".to_vec()),
+            (Characteristic::SerialNumber, b"0000000000".to_vec()),
+            (Characteristic::HardwareRevision, b"1.0".to_vec()),
+            (Characteristic::FirmwareRevision, b"3.4.0".to_vec()),
+            (Characteristic::SoftwareRevision, b"3.4.0".to_vec()),
+        ]);
+        SimulatedTap {
+            address,
+            name: String::from("Tap Simulated"),
+            state: Mutex::new(SimulatedState { values, ..Default::default() }),
+        }
+    }
+
+    pub fn with_name(mut self, name: &str) -> Self {
+        self.name = name.to_string();
+        self
+    }
+
+    // Reports the model in its device information.
+    pub fn with_model(self, model: TapModel) -> Self {
+        self.set_value(Characteristic::ModelNumber, Some(model.to_string().into_bytes()));
+        self
+    }
+
+    // Leaves a characteristic out, like a model without that sensor.
+    pub fn without_characteristic(self, characteristic: Characteristic) -> Self {
+        self.state.lock().unwrap().missing.insert(characteristic);
+        self
+    }
+
+    // An out of range device drops its link and refuses to connect.
+    pub fn set_in_range(&self, in_range: bool) {
+        let mut state = self.state.lock().unwrap();
+        state.out_of_range = !in_range;
+        if !in_range && state.connected {
+            state.drop_link();
+        }
+    }
+
+    pub fn written_commands(&self) -> Vec<(Characteristic, Vec<u8>)> {
+        self.state.lock().unwrap().written.clone()
+    }
+
+    pub fn clear_written_commands(&self) {
+        self.state.lock().unwrap().written.clear();
+    }
+
+    pub fn is_subscribed(&self, characteristic: Characteristic) -> bool {
+        let uuid = Uuid::from_u128(characteristic.as_uuid());
+        self.state.lock().unwrap().subscribed.contains(&uuid)
+    }
+
+    // The value returned when the characteristic is read. `None` removes a
+    // device information characteristic, like on a device without it.
+    pub fn set_value(&self, characteristic: Characteristic, value: Option<Vec<u8>>) {
+        let mut state = self.state.lock().unwrap();
+        match value {
+            Some(value) => state.values.insert(characteristic, value),
+            None => state.values.remove(&characteristic),
+        };
+    }
+
+    pub fn set_battery_level(&self, level: u8) {
+        self.set_value(Characteristic::BatteryLevel, Some(vec![level]));
+        self.push_notification(Characteristic::BatteryLevel, vec![level]);
+    }
+
+    pub fn push_tap(&self, tapcode: u8) {
+        self.push_notification(Characteristic::TapData, vec![tapcode]);
+    }
+
+    pub fn push_mouse(&self, vx: i16, vy: i16, proximity: bool) {
+        let [vx_high, vx_low] = vx.to_be_bytes();
+        let [vy_high, vy_low] = vy.to_be_bytes();
+        self.push_notification(
+            Characteristic::MouseData,
+            vec![vx_high, vx_low, vy_high, vy_low, proximity as u8],
+        );
+    }
+
+    pub fn push_air_gesture(&self, gesture: AirGestures) {
+        self.push_notification(Characteristic::AirGestureData, vec![gesture as u8]);
+    }
+
+    pub fn push_air_gesture_state(&self, in_air_gesture_state: bool) {
+        self.push_notification(Characteristic::AirGestureData, vec![0x14, in_air_gesture_state as u8]);
+    }
+
+    pub fn push_raw(&self, data: Vec<u8>) {
+        self.push_notification(Characteristic::RawSensors, data);
+    }
+
+    // Notifications are only delivered while connected and subscribed,
+    // the same way a real peripheral behaves.
+    pub fn push_notification(&self, characteristic: Characteristic, value: Vec<u8>) {
+        let uuid = Uuid::from_u128(characteristic.as_uuid());
+        let mut state = self.state.lock().unwrap();
+        if !state.connected || !state.subscribed.contains(&uuid) {
+            return;
+        }
+
+        let notification = ValueNotification { uuid, value };
+        state
+            .listeners
+            .retain(|listener| listener.unbounded_send(notification.clone()).is_ok());
+    }
+}
+
+#[async_trait]
+impl TapTransport for SimulatedTap {
+    fn address(&self) -> BDAddr {
+        self.address
+    }
+
+    async fn name(&self) -> Option<String> {
+        Some(self.name.clone())
+    }
+
+    async fn connect(&self) -> Result<(), TapError> {
+        let mut state = self.state.lock().unwrap();
+        if state.out_of_range {
+            return Err(TapError::DeviceNotFound);
+        }
+        state.set_connection_state(ConnectionState::Connected);
+        Ok(())
+    }
+
+    async fn disconnect(&self) -> Result<(), TapError> {
+        self.state.lock().unwrap().drop_link();
+        Ok(())
+    }
+
+    async fn is_connected(&self) -> Result<bool, TapError> {
+        Ok(self.state.lock().unwrap().connected)
+    }
+
+    fn characteristics(&self) -> BTreeSet<GattCharacteristic> {
+        let state = self.state.lock().unwrap();
+        Characteristic::iter()
+            .filter(|characteristic| !state.missing.contains(characteristic))
+            .filter(|characteristic| characteristic.service() != Service::DeviceInformation || state.values.contains_key(characteristic))
+            .map(|characteristic| GattCharacteristic {
+                uuid: Uuid::from_u128(characteristic.as_uuid()),
+                service_uuid: Uuid::from_u128(characteristic.service().as_uuid()),
+                properties: characteristic.properties(),
+                descriptors: BTreeSet::new(),
+            })
+            .collect()
+    }
+
+    async fn write(&self, characteristic: &GattCharacteristic, data: &[u8]) -> Result<(), TapError> {
+        let mut state = self.state.lock().unwrap();
+        if !state.connected {
+            return Err(TapError::NotConnected);
+        }
+        if !characteristic.properties.contains(CharPropFlags::WRITE) {
+            return Err(TapError::Protocol(format!("Characteristic {:?} is not writable", characteristic.uuid)));
+        }
+
+        let tap_characteristic = Characteristic::from_uuid(characteristic.uuid)?;
+        state.written.push((tap_characteristic, data.to_vec()));
+        Ok(())
+    }
+
+    async fn read(&self, characteristic: &GattCharacteristic) -> Result<Vec<u8>, TapError> {
+        let state = self.state.lock().unwrap();
+        if !state.connected {
+            return Err(TapError::NotConnected);
+        }
+        if !characteristic.properties.contains(CharPropFlags::READ) {
+            return Err(TapError::Protocol(format!("Characteristic {:?} is not readable", characteristic.uuid)));
+        }
+
+        let tap_characteristic = Characteristic::from_uuid(characteristic.uuid)?;
+        Ok(state.values.get(&tap_characteristic).cloned().unwrap_or_default())
+    }
+
+    async fn subscribe(&self, characteristic: &GattCharacteristic) -> Result<(), TapError> {
+        let mut state = self.state.lock().unwrap();
+        if !state.connected {
+            return Err(TapError::NotConnected);
+        }
+        if !characteristic.properties.contains(CharPropFlags::NOTIFY) {
+            return Err(TapError::Protocol(format!("Characteristic {:?} does not support notifications", characteristic.uuid)));
+        }
+
+        state.subscribed.insert(characteristic.uuid);
+        Ok(())
+    }
+
+    async fn unsubscribe(&self, characteristic: &GattCharacteristic) -> Result<(), TapError> {
+        self.state.lock().unwrap().subscribed.remove(&characteristic.uuid);
+        Ok(())
+    }
+
+    async fn notifications(&self) -> Result<NotificationStream, TapError> {
+        let (sender, receiver) = mpsc::unbounded();
+        self.state.lock().unwrap().listeners.push(sender);
+        Ok(Box::pin(receiver))
+    }
+
+    async fn connection_events(&self) -> Result<ConnectionStream, TapError> {
+        let (sender, receiver) = mpsc::unbounded();
+        self.state.lock().unwrap().connection_listeners.push(sender);
+        Ok(Box::pin(receiver))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::StreamExt;
+
+    fn gatt_characteristic(simulated_tap: &SimulatedTap, characteristic: Characteristic) -> GattCharacteristic {
+        let uuid = Uuid::from_u128(characteristic.as_uuid());
+        simulated_tap.characteristics().into_iter().find(|gatt| gatt.uuid == uuid).unwrap()
+    }
+
+    #[tokio::test]
+    async fn delivers_notifications_only_while_subscribed() {
+        let simulated_tap = SimulatedTap::new();
+        let tap_data = gatt_characteristic(&simulated_tap, Characteristic::TapData);
+        assert!(matches!(simulated_tap.subscribe(&tap_data).await, Err(TapError::NotConnected)));
+
+        simulated_tap.connect().await.unwrap();
+        let notifications = simulated_tap.notifications().await.unwrap();
+        simulated_tap.push_tap(1);
+        simulated_tap.subscribe(&tap_data).await.unwrap();
+        assert!(simulated_tap.is_subscribed(Characteristic::TapData));
+        simulated_tap.push_tap(2);
+        simulated_tap.unsubscribe(&tap_data).await.unwrap();
+        assert!(!simulated_tap.is_subscribed(Characteristic::TapData));
+        simulated_tap.push_tap(3);
+        simulated_tap.disconnect().await.unwrap();
+
+        // Disconnecting ends the stream.
+        let values: Vec<Vec<u8>> = notifications.map(|notification| notification.value).collect().await;
+        assert_eq!(values, vec![vec![2]]);
+    }
+}
@@ -0,0 +1,66 @@
+use btleplug::api::BDAddr;
+use std::fmt;
+use std::time::SystemTime;
+
+// [markdown]
+// `DeviceId` identifies a Tap device by its Bluetooth address,
+// which stays the same across connections.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Default)]
+pub struct DeviceId(BDAddr);
+
+impl DeviceId {
+    pub fn new(address: BDAddr) -> Self {
+        DeviceId(address)
+    }
+
+    pub fn address(&self) -> BDAddr {
+        self.0
+    }
+}
+
+impl From<BDAddr> for DeviceId {
+    fn from(address: BDAddr) -> Self {
+        DeviceId(address)
+    }
+}
+
+impl fmt::Display for DeviceId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ConnectionState {
+    Connected,
+    Disconnected,
+}
+
+// [markdown]
+// `EventSource` is passed to every listener: the device that sent the
+// event, its friendly name and the host time the event was received at.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EventSource {
+    pub device: DeviceId,
+    pub name: Option<String>,
+    pub received_at: SystemTime,
+}
+
+impl EventSource {
+    pub fn new(device: DeviceId, name: Option<String>) -> Self {
+        EventSource {
+            device,
+            name,
+            received_at: SystemTime::now(),
+        }
+    }
+}
+
+impl fmt::Display for EventSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.name {
+            Some(name) => write!(f, "{} ({})", name, self.device),
+            None => write!(f, "{}", self.device),
+        }
+    }
+}
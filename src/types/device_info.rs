@@ -0,0 +1,31 @@
+use std::fmt;
+
+// [markdown]
+// `DeviceInfo` holds what the standard GATT Device Information service
+// reports about a Tap device. A field is `None` when the device does not
+// expose that characteristic.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct DeviceInfo {
+    pub manufacturer: Option<String>,
+    pub model_number: Option<String>,
+    pub serial_number: Option<String>,
+    pub hardware_revision: Option<String>,
+    pub firmware_revision: Option<String>,
+    pub software_revision: Option<String>,
+}
+
+impl fmt::Display for DeviceInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let unknown = String::from("?");
+        write!(
+            f,
+            "{} {} (serial {}, hw {}, fw {}, sw {})",
+            self.manufacturer.as_ref().unwrap_or(&unknown),
+            self.model_number.as_ref().unwrap_or(&unknown),
+            self.serial_number.as_ref().unwrap_or(&unknown),
+            self.hardware_revision.as_ref().unwrap_or(&unknown),
+            self.firmware_revision.as_ref().unwrap_or(&unknown),
+            self.software_revision.as_ref().unwrap_or(&unknown),
+        )
+    }
+}
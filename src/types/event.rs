@@ -0,0 +1,110 @@
+use futures::stream::Stream;
+use std::pin::Pin;
+
+use crate::types::air_gestures::AirGestures;
+use crate::types::device::{ConnectionState, EventSource};
+use crate::types::raw_sensors::RawSensorPacket;
+use crate::types::tap_code::TapCode;
+use crate::types::uuid::characteristics::Characteristic;
+
+pub type EventStream = Pin<Box<dyn Stream<Item = TapEvent> + Send>>;
+
+// [markdown]
+// `TapEvent` is a single event from a Tap device, as delivered by
+// `Client::events`. It carries the same data as the listener callbacks.
+#[derive(Debug, Clone, PartialEq)]
+pub enum TapEvent {
+    Tap { source: EventSource, tap_code: TapCode },
+    Mouse { source: EventSource, vx: i16, vy: i16, proximity: bool },
+    AirGesture { source: EventSource, gesture: AirGestures },
+    AirGestureState { source: EventSource, in_air_gesture_state: bool },
+    Raw { source: EventSource, data: Vec<u8> },
+    Battery { source: EventSource, level: u8 },
+    Connection { source: EventSource, state: ConnectionState },
+    // The stream was not polled fast enough and `missed` events were dropped.
+    Lagged { source: EventSource, missed: u64 },
+}
+
+impl TapEvent {
+    pub fn source(&self) -> &EventSource {
+        match self {
+            TapEvent::Tap { source, .. }
+            | TapEvent::Mouse { source, .. }
+            | TapEvent::AirGesture { source, .. }
+            | TapEvent::AirGestureState { source, .. }
+            | TapEvent::Raw { source, .. }
+            | TapEvent::Battery { source, .. }
+            | TapEvent::Connection { source, .. }
+            | TapEvent::Lagged { source, .. } => source,
+        }
+    }
+
+    // Only set for `Raw` events.
+    pub fn raw_sensor_packet(&self) -> Option<RawSensorPacket> {
+        match self {
+            TapEvent::Raw { data, .. } => Some(RawSensorPacket::parse(data)),
+            _ => None,
+        }
+    }
+
+    // Decodes a notification received on one of the Tap characteristics.
+    pub fn decode(characteristic: Characteristic, source: EventSource, data: &[u8]) -> Option<TapEvent> {
+        match characteristic {
+            Characteristic::TapData => decode_tap(data).map(|tap_code| TapEvent::Tap { source, tap_code }),
+            Characteristic::MouseData => {
+                decode_mouse(data).map(|(vx, vy, proximity)| TapEvent::Mouse { source, vx, vy, proximity })
+            }
+            Characteristic::AirGestureData if is_air_gesture_state(data) => {
+                decode_air_gesture_state(data).map(|in_air_gesture_state| TapEvent::AirGestureState { source, in_air_gesture_state })
+            }
+            Characteristic::AirGestureData => {
+                decode_air_gesture(data).map(|gesture| TapEvent::AirGesture { source, gesture })
+            }
+            Characteristic::RawSensors => Some(TapEvent::Raw { source, data: data.to_vec() }),
+            Characteristic::BatteryLevel => decode_battery(data).map(|level| TapEvent::Battery { source, level }),
+            Characteristic::UiCmd
+            | Characteristic::TapMode
+            | Characteristic::ManufacturerName
+            | Characteristic::ModelNumber
+            | Characteristic::SerialNumber
+            | Characteristic::HardwareRevision
+            | Characteristic::FirmwareRevision
+            | Characteristic::SoftwareRevision => None,
+        }
+    }
+}
+
+// Air gesture state changes share the air gesture characteristic, prefixed by 0x14.
+fn is_air_gesture_state(data: &[u8]) -> bool {
+    data.first() == Some(&0x14)
+}
+
+pub(crate) fn decode_air_gesture(data: &[u8]) -> Option<AirGestures> {
+    data.first().map(|gesture_value| AirGestures::from_u8(*gesture_value))
+}
+
+pub(crate) fn decode_tap(data: &[u8]) -> Option<TapCode> {
+    data.first().map(|tapcode| TapCode::from(*tapcode))
+}
+
+pub(crate) fn decode_mouse(data: &[u8]) -> Option<(i16, i16, bool)> {
+    if data.len() < 4 {
+        return None;
+    }
+    let vx = ((data[0] as i16) << 8) | (data[1] as i16);
+    let vy = ((data[2] as i16) << 8) | (data[3] as i16);
+    let proximity = data.len() > 4 && data[4] != 0;
+    Some((vx, vy, proximity))
+}
+
+pub(crate) fn decode_air_gesture_state(data: &[u8]) -> Option<bool> {
+    if data.is_empty() {
+        return None;
+    }
+    Some(is_air_gesture_state(data) && data.len() > 1 && data[1] != 0)
+}
+
+// Battery level in percent.
+pub(crate) fn decode_battery(data: &[u8]) -> Option<u8> {
+    data.first().copied()
+}
@@ -0,0 +1,86 @@
+use std::fmt;
+
+// [markdown]
+// `FirmwareVersion` is the firmware revision reported by the Device
+// Information service, parsed so versions can be compared.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct FirmwareVersion {
+    pub major: u16,
+    pub minor: u16,
+    pub patch: u16,
+}
+
+impl FirmwareVersion {
+    pub const fn new(major: u16, minor: u16, patch: u16) -> Self {
+        FirmwareVersion { major, minor, patch }
+    }
+
+    // Accepts revisions like "3.4.1", "v3.4" or "FW 3.4.1-beta",
+    // missing parts are 0.
+    pub fn parse(revision: &str) -> Option<Self> {
+        let start = revision.find(|c: char| c.is_ascii_digit())?;
+        let mut parts = revision[start..]
+            .split(|c: char| !c.is_ascii_digit() && c != '.')
+            .next()?
+            .split('.')
+            .map(|part| part.parse::<u16>());
+
+        let major = parts.next()?.ok()?;
+        let minor = parts.next().map_or(Some(0), |part| part.ok())?;
+        let patch = parts.next().map_or(Some(0), |part| part.ok())?;
+        Some(FirmwareVersion::new(major, minor, patch))
+    }
+}
+
+impl fmt::Display for FirmwareVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}.{}", self.major, self.minor, self.patch)
+    }
+}
+
+// Commands that older firmware silently ignores. Tap does not publish the
+// firmware versions that introduced them, so nothing is gated unless the
+// application sets a minimum with `ClientBuilder::min_firmware_version`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum FirmwareFeature {
+    InputType,
+    AirGestureState,
+}
+
+impl fmt::Display for FirmwareFeature {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            FirmwareFeature::InputType => "Input type selection",
+            FirmwareFeature::AirGestureState => "Extended air gesture state",
+        };
+        f.write_str(name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_revisions() {
+        assert_eq!(FirmwareVersion::parse("3.4.1"), Some(FirmwareVersion::new(3, 4, 1)));
+        assert_eq!(FirmwareVersion::parse("v3.4"), Some(FirmwareVersion::new(3, 4, 0)));
+        assert_eq!(FirmwareVersion::parse("FW 3.4.1-beta"), Some(FirmwareVersion::new(3, 4, 1)));
+        assert_eq!(FirmwareVersion::parse("2"), Some(FirmwareVersion::new(2, 0, 0)));
+    }
+
+    #[test]
+    fn rejects_invalid_revisions() {
+        for revision in ["", "beta", "3..1", "99999.1"] {
+            assert_eq!(FirmwareVersion::parse(revision), None, "{:?}", revision);
+        }
+    }
+
+    #[test]
+    fn compares_versions() {
+        assert!(FirmwareVersion::new(3, 4, 1) > FirmwareVersion::new(3, 4, 0));
+        assert!(FirmwareVersion::new(3, 10, 0) > FirmwareVersion::new(3, 9, 9));
+        assert!(FirmwareVersion::new(4, 0, 0) > FirmwareVersion::new(3, 99, 99));
+        assert_eq!(FirmwareVersion::new(3, 4, 1).to_string(), "3.4.1");
+    }
+}
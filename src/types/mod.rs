@@ -2,3 +2,11 @@ pub mod uuid;
 pub mod input_type;
 pub mod mouse_mode;
 pub mod air_gestures;
+pub mod raw_sensors;
+pub mod sensitivity;
+pub mod tap_code;
+pub mod device;
+pub mod device_info;
+pub mod event;
+pub mod firmware;
+pub mod model;
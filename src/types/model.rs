@@ -0,0 +1,116 @@
+use std::fmt;
+
+use crate::types::device_info::DeviceInfo;
+use crate::types::uuid::characteristics::Characteristic;
+
+// [markdown]
+// `TapModel` is the kind of Tap device. During discovery it is guessed from
+// the advertised name, which is `Unknown` for renamed devices. Once connected,
+// the client detects it from the device information and characteristics too.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum TapModel {
+    TapStrap,
+    TapStrap2,
+    TapXR,
+    #[default]
+    Unknown,
+}
+
+impl TapModel {
+    pub fn from_name(name: &str) -> Self {
+        let name = name.to_lowercase().replace([' ', '_', '-'], "");
+        if name.starts_with("tapxr") {
+            TapModel::TapXR
+        } else if name.starts_with("tapstrap2") {
+            TapModel::TapStrap2
+        } else if name.starts_with("tapstrap") {
+            TapModel::TapStrap
+        } else {
+            TapModel::Unknown
+        }
+    }
+
+    // The reported model number wins over the name, which users can change.
+    // Only Tap Strap has no air gesture characteristic.
+    pub fn detect(name: Option<&str>, info: &DeviceInfo, characteristics: &[Characteristic]) -> Self {
+        let from_strings = [info.model_number.as_deref(), name]
+            .into_iter()
+            .flatten()
+            .map(TapModel::from_name)
+            .find(|model| *model != TapModel::Unknown);
+        if let Some(model) = from_strings {
+            return model;
+        }
+
+        if characteristics.contains(&Characteristic::TapData) && !characteristics.contains(&Characteristic::AirGestureData) {
+            TapModel::TapStrap
+        } else {
+            TapModel::Unknown
+        }
+    }
+
+    pub fn capabilities(&self) -> Capabilities {
+        match self {
+            TapModel::TapStrap => Capabilities {
+                finger_accelerometers: true,
+                thumb_imu: false,
+                optical_mouse: true,
+                air_mouse: false,
+                air_gestures: false,
+                spatial_control: false,
+            },
+            TapModel::TapStrap2 => Capabilities {
+                finger_accelerometers: true,
+                thumb_imu: true,
+                optical_mouse: true,
+                air_mouse: true,
+                air_gestures: true,
+                spatial_control: false,
+            },
+            TapModel::TapXR => Capabilities {
+                finger_accelerometers: false,
+                thumb_imu: true,
+                optical_mouse: false,
+                air_mouse: true,
+                air_gestures: true,
+                spatial_control: true,
+            },
+            // Nothing is known, so nothing is refused.
+            TapModel::Unknown => Capabilities {
+                finger_accelerometers: true,
+                thumb_imu: true,
+                optical_mouse: true,
+                air_mouse: true,
+                air_gestures: true,
+                spatial_control: true,
+            },
+        }
+    }
+}
+
+// [markdown]
+// `Capabilities` lists the hardware features of a `TapModel`:
+// five finger accelerometers on Tap Strap and Tap Strap 2, the thumb IMU on
+// Tap Strap 2 and TapXR, and Spatial Control (extended air gesture state and
+// input type selection) only on TapXR.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Capabilities {
+    pub finger_accelerometers: bool,
+    pub thumb_imu: bool,
+    pub optical_mouse: bool,
+    pub air_mouse: bool,
+    pub air_gestures: bool,
+    pub spatial_control: bool,
+}
+
+impl fmt::Display for TapModel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            TapModel::TapStrap => "Tap Strap",
+            TapModel::TapStrap2 => "Tap Strap 2",
+            TapModel::TapXR => "TapXR",
+            TapModel::Unknown => "Unknown Tap",
+        };
+        f.write_str(name)
+    }
+}
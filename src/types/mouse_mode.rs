@@ -10,4 +10,8 @@ impl MouseModes {
     pub fn to_uid(&self) -> u8 {
         *self as u8
     }
+
+    pub fn to_command(&self) -> Vec<u8> {
+        vec![0x03, 0x0e, 0x00, self.to_uid()]
+    }
 }
@@ -0,0 +1,202 @@
+use super::sensitivity::RawSensorConfig;
+
+const TIMESTAMP_SIZE: usize = 4;
+const VALUE_SIZE: usize = 2;
+const FINGER_ACCEL_FLAG: u32 = 0x8000_0000;
+const IMU_VALUES: usize = 6;
+const FINGER_ACCEL_VALUES: usize = 15;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ImuSample {
+    pub timestamp: u32,
+    pub gyro: [i16; 3],
+    pub accel: [i16; 3],
+}
+
+// Fingers are ordered thumb, index, middle, ring, pinky.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FingerAccelSample {
+    pub timestamp: u32,
+    pub fingers: [[i16; 3]; 5],
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RawSensorMessage {
+    Imu(ImuSample),
+    FingerAccel(FingerAccelSample),
+}
+
+impl ImuSample {
+    // deg/s
+    pub fn gyro_dps(&self, config: &RawSensorConfig) -> [f32; 3] {
+        let scale = config.gyro.scale_factor() / 1000.0;
+        self.gyro.map(|value| value as f32 * scale)
+    }
+
+    // g
+    pub fn accel_g(&self, config: &RawSensorConfig) -> [f32; 3] {
+        let scale = config.imu_accel.scale_factor() / 1000.0;
+        self.accel.map(|value| value as f32 * scale)
+    }
+}
+
+impl FingerAccelSample {
+    // g
+    pub fn accel_g(&self, config: &RawSensorConfig) -> [[f32; 3]; 5] {
+        let scale = config.finger_accel.scale_factor() / 1000.0;
+        self.fingers.map(|axes| axes.map(|value| value as f32 * scale))
+    }
+}
+
+impl RawSensorMessage {
+    pub fn timestamp(&self) -> u32 {
+        match self {
+            RawSensorMessage::Imu(sample) => sample.timestamp,
+            RawSensorMessage::FingerAccel(sample) => sample.timestamp,
+        }
+    }
+}
+
+// [markdown]
+// `RawSensorPacket` is a decoded RawSensors notification.
+// A notification holds one or more messages, each made of a little-endian
+// millisecond timestamp followed by signed 16-bit values. The highest bit of
+// the timestamp marks a finger accelerometers message (15 values), otherwise
+// the message comes from the thumb IMU (gyro xyz, then accelerometer xyz).
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct RawSensorPacket {
+    pub messages: Vec<RawSensorMessage>,
+    pub truncated: bool,
+}
+
+impl RawSensorPacket {
+    pub fn parse(data: &[u8]) -> Self {
+        let mut packet = RawSensorPacket::default();
+        let mut offset = 0;
+
+        while offset + TIMESTAMP_SIZE <= data.len() {
+            let timestamp = u32::from_le_bytes([
+                data[offset],
+                data[offset + 1],
+                data[offset + 2],
+                data[offset + 3],
+            ]);
+            // The rest of the packet is zero padding.
+            if timestamp == 0 {
+                return packet;
+            }
+            offset += TIMESTAMP_SIZE;
+
+            let is_finger_accel = timestamp & FINGER_ACCEL_FLAG != 0;
+            let timestamp = timestamp & !FINGER_ACCEL_FLAG;
+            let values_count = if is_finger_accel { FINGER_ACCEL_VALUES } else { IMU_VALUES };
+
+            let end = offset + values_count * VALUE_SIZE;
+            if end > data.len() {
+                packet.truncated = true;
+                return packet;
+            }
+            let values: Vec<i16> = data[offset..end]
+                .chunks_exact(VALUE_SIZE)
+                .map(|value| i16::from_le_bytes([value[0], value[1]]))
+                .collect();
+            offset = end;
+
+            let message = if is_finger_accel {
+                let mut fingers = [[0; 3]; 5];
+                for (finger, axes) in fingers.iter_mut().zip(values.chunks_exact(3)) {
+                    finger.copy_from_slice(axes);
+                }
+                RawSensorMessage::FingerAccel(FingerAccelSample { timestamp, fingers })
+            } else {
+                RawSensorMessage::Imu(ImuSample {
+                    timestamp,
+                    gyro: [values[0], values[1], values[2]],
+                    accel: [values[3], values[4], values[5]],
+                })
+            };
+            packet.messages.push(message);
+        }
+
+        packet.truncated = offset != data.len();
+        packet
+    }
+
+    pub fn imu_samples(&self) -> impl Iterator<Item = &ImuSample> {
+        self.messages.iter().filter_map(|message| match message {
+            RawSensorMessage::Imu(sample) => Some(sample),
+            _ => None,
+        })
+    }
+
+    pub fn finger_accel_samples(&self) -> impl Iterator<Item = &FingerAccelSample> {
+        self.messages.iter().filter_map(|message| match message {
+            RawSensorMessage::FingerAccel(sample) => Some(sample),
+            _ => None,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn message(timestamp: u32, values: &[i16]) -> Vec<u8> {
+        let mut data = timestamp.to_le_bytes().to_vec();
+        data.extend(values.iter().flat_map(|value| value.to_le_bytes()));
+        data
+    }
+
+    #[test]
+    fn parses_imu_and_finger_accel_messages() {
+        let fingers: Vec<i16> = (1..=15).collect();
+        let mut data = message(100, &[1, -2, 3, -4, 5, -6]);
+        data.extend(message(200 | FINGER_ACCEL_FLAG, &fingers));
+
+        let packet = RawSensorPacket::parse(&data);
+        assert!(!packet.truncated);
+        assert_eq!(packet.messages, vec![
+            RawSensorMessage::Imu(ImuSample { timestamp: 100, gyro: [1, -2, 3], accel: [-4, 5, -6] }),
+            RawSensorMessage::FingerAccel(FingerAccelSample {
+                timestamp: 200,
+                fingers: [[1, 2, 3], [4, 5, 6], [7, 8, 9], [10, 11, 12], [13, 14, 15]],
+            }),
+        ]);
+        assert_eq!(packet.imu_samples().count(), 1);
+        assert_eq!(packet.finger_accel_samples().count(), 1);
+    }
+
+    #[test]
+    fn stops_at_zero_padding() {
+        let mut data = message(100, &[0; IMU_VALUES]);
+        data.extend([0; 12]);
+
+        let packet = RawSensorPacket::parse(&data);
+        assert_eq!(packet.messages.len(), 1);
+        assert!(!packet.truncated);
+    }
+
+    #[test]
+    fn marks_truncated_messages() {
+        let mut data = message(100, &[1, 2, 3, 4, 5, 6]);
+        data.extend(&message(200, &[1, 2, 3, 4, 5, 6])[..7]);
+
+        let packet = RawSensorPacket::parse(&data);
+        assert_eq!(packet.messages.len(), 1);
+        assert!(packet.truncated);
+
+        // Fewer bytes left than a timestamp.
+        let packet = RawSensorPacket::parse(&data[..data.len() - 5]);
+        assert_eq!(packet.messages.len(), 1);
+        assert!(packet.truncated);
+    }
+
+    #[test]
+    fn does_not_panic_on_arbitrary_input() {
+        assert_eq!(RawSensorPacket::parse(&[]), RawSensorPacket::default());
+        for length in 0..64 {
+            let data: Vec<u8> = (0..length).map(|byte| (byte * 37 + 0x81) as u8).collect();
+            RawSensorPacket::parse(&data);
+        }
+    }
+}
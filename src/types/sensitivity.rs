@@ -0,0 +1,118 @@
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FingerAccelRange {
+    G2 = 1,
+    G4 = 2,
+    G8 = 3,
+    #[default]
+    G16 = 4,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum GyroRange {
+    Dps125 = 1,
+    Dps250 = 2,
+    #[default]
+    Dps500 = 3,
+    Dps1000 = 4,
+    Dps2000 = 5,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ImuAccelRange {
+    G2 = 1,
+    #[default]
+    G4 = 2,
+    G8 = 3,
+    G16 = 4,
+}
+
+impl FingerAccelRange {
+    // 0 selects the device default.
+    pub fn from_u8(value: u8) -> Self {
+        match value {
+            1 => FingerAccelRange::G2,
+            2 => FingerAccelRange::G4,
+            3 => FingerAccelRange::G8,
+            _ => FingerAccelRange::G16,
+        }
+    }
+
+    // mg/LSB
+    pub fn scale_factor(&self) -> f32 {
+        match self {
+            FingerAccelRange::G2 => 3.91,
+            FingerAccelRange::G4 => 7.81,
+            FingerAccelRange::G8 => 15.62,
+            FingerAccelRange::G16 => 31.25,
+        }
+    }
+}
+
+impl GyroRange {
+    pub fn from_u8(value: u8) -> Self {
+        match value {
+            1 => GyroRange::Dps125,
+            2 => GyroRange::Dps250,
+            4 => GyroRange::Dps1000,
+            5 => GyroRange::Dps2000,
+            _ => GyroRange::Dps500,
+        }
+    }
+
+    // mdps/LSB
+    pub fn scale_factor(&self) -> f32 {
+        match self {
+            GyroRange::Dps125 => 4.375,
+            GyroRange::Dps250 => 8.75,
+            GyroRange::Dps500 => 17.5,
+            GyroRange::Dps1000 => 35.0,
+            GyroRange::Dps2000 => 70.0,
+        }
+    }
+}
+
+impl ImuAccelRange {
+    pub fn from_u8(value: u8) -> Self {
+        match value {
+            1 => ImuAccelRange::G2,
+            3 => ImuAccelRange::G8,
+            4 => ImuAccelRange::G16,
+            _ => ImuAccelRange::G4,
+        }
+    }
+
+    // mg/LSB
+    pub fn scale_factor(&self) -> f32 {
+        match self {
+            ImuAccelRange::G2 => 0.061,
+            ImuAccelRange::G4 => 0.122,
+            ImuAccelRange::G8 => 0.244,
+            ImuAccelRange::G16 => 0.488,
+        }
+    }
+}
+
+// [markdown]
+// `RawSensorConfig` holds the dynamic range of every raw sensor.
+// It is sent with the Raw mode command as the `sensitivity` list.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct RawSensorConfig {
+    pub finger_accel: FingerAccelRange,
+    pub gyro: GyroRange,
+    pub imu_accel: ImuAccelRange,
+}
+
+impl RawSensorConfig {
+    pub fn from_sensitivity(sensitivity: &[u8]) -> Self {
+        let value = |index: usize| sensitivity.get(index).copied().unwrap_or(0);
+        RawSensorConfig {
+            finger_accel: FingerAccelRange::from_u8(value(0)),
+            gyro: GyroRange::from_u8(value(1)),
+            imu_accel: ImuAccelRange::from_u8(value(2)),
+        }
+    }
+
+    pub fn to_sensitivity(&self) -> Vec<u8> {
+        vec![self.finger_accel as u8, self.gyro as u8, self.imu_accel as u8]
+    }
+}
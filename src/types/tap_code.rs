@@ -0,0 +1,159 @@
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::error::Error;
+use std::fmt;
+use std::str::FromStr;
+use strum::IntoEnumIterator;
+use strum_macros::EnumIter;
+
+#[derive(EnumIter, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Finger {
+    Thumb = 0,
+    Index = 1,
+    Middle = 2,
+    Ring = 3,
+    Pinky = 4,
+}
+
+impl Finger {
+    pub fn mask(&self) -> u8 {
+        1 << (*self as u8)
+    }
+}
+
+// [markdown]
+// `TapCode` is the tap byte sent by the device.
+// Bit 0 is the thumb and bit 4 is the pinky. As a string it is written
+// thumb first, e.g. `"10010"` is thumb and ring finger.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct TapCode(u8);
+
+impl TapCode {
+    pub fn new(value: u8) -> Self {
+        TapCode(value & 0b11111)
+    }
+
+    pub fn from_fingers(fingers: &[Finger]) -> Self {
+        TapCode(fingers.iter().fold(0, |value, finger| value | finger.mask()))
+    }
+
+    pub fn as_u8(&self) -> u8 {
+        self.0
+    }
+
+    pub fn fingers(&self) -> Vec<Finger> {
+        Finger::iter().filter(|finger| self.is_finger_down(*finger)).collect()
+    }
+
+    pub fn is_finger_down(&self, finger: Finger) -> bool {
+        self.0 & finger.mask() != 0
+    }
+
+    pub fn count(&self) -> u32 {
+        self.0.count_ones()
+    }
+}
+
+impl From<u8> for TapCode {
+    fn from(value: u8) -> Self {
+        TapCode::new(value)
+    }
+}
+
+impl From<TapCode> for u8 {
+    fn from(tap_code: TapCode) -> Self {
+        tap_code.0
+    }
+}
+
+impl fmt::Display for TapCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for finger in Finger::iter() {
+            write!(f, "{}", if self.is_finger_down(finger) { '●' } else { '○' })?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseTapCodeError(String);
+
+impl fmt::Display for ParseTapCodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Invalid tap code {:?}, expected five fingers written as 1/0 or ●/○, thumb first", self.0)
+    }
+}
+
+impl Error for ParseTapCodeError {}
+
+impl FromStr for TapCode {
+    type Err = ParseTapCodeError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let symbols: Vec<char> = s.trim().chars().collect();
+        if symbols.len() != 5 {
+            return Err(ParseTapCodeError(s.to_string()));
+        }
+
+        let mut value = 0;
+        for (finger, symbol) in Finger::iter().zip(symbols) {
+            match symbol {
+                '1' | '●' => value |= finger.mask(),
+                '0' | '○' => {}
+                _ => return Err(ParseTapCodeError(s.to_string())),
+            }
+        }
+        Ok(TapCode(value))
+    }
+}
+
+impl Serialize for TapCode {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let code: String = Finger::iter()
+            .map(|finger| if self.is_finger_down(finger) { '1' } else { '0' })
+            .collect();
+        serializer.serialize_str(&code)
+    }
+}
+
+impl<'de> Deserialize<'de> for TapCode {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let code = String::deserialize(deserializer)?;
+        code.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_thumb_first() {
+        let tap_code: TapCode = "10010".parse().unwrap();
+        assert_eq!(tap_code.as_u8(), 0b01001);
+        assert_eq!(tap_code.fingers(), vec![Finger::Thumb, Finger::Ring]);
+        assert_eq!(" ●○○●○ ".parse::<TapCode>(), Ok(tap_code));
+    }
+
+    #[test]
+    fn rejects_invalid_codes() {
+        for code in ["", "1001", "100100", "10020", "abcde"] {
+            assert!(code.parse::<TapCode>().is_err(), "{:?}", code);
+        }
+    }
+
+    #[test]
+    fn displays_fingers() {
+        assert_eq!(TapCode::new(0b10001).to_string(), "●○○○●");
+        assert_eq!(TapCode::new(0).to_string(), "○○○○○");
+    }
+
+    #[test]
+    fn round_trips_all_codes() {
+        for value in 0..32 {
+            let tap_code = TapCode::new(value);
+            assert_eq!(tap_code.to_string().parse::<TapCode>(), Ok(tap_code));
+            let json = serde_json::to_string(&tap_code).unwrap();
+            assert_eq!(serde_json::from_str::<TapCode>(&json).unwrap(), tap_code);
+        }
+    }
+}
@@ -1,9 +1,13 @@
+use btleplug::api::CharPropFlags;
+use serde::{Deserialize, Serialize};
 use strum::IntoEnumIterator;
 use strum_macros::EnumIter;
 use uuid::Uuid;
-use std::error::Error;
 
-#[derive(EnumIter, Debug)]
+use super::services::Service;
+use crate::error::TapError;
+
+#[derive(EnumIter, Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum Characteristic {
     TapData,
     MouseData,
@@ -11,6 +15,13 @@ pub enum Characteristic {
     UiCmd,
     TapMode,
     RawSensors,
+    BatteryLevel,
+    ManufacturerName,
+    ModelNumber,
+    SerialNumber,
+    HardwareRevision,
+    FirmwareRevision,
+    SoftwareRevision,
 }
 
 impl Characteristic {
@@ -22,6 +33,50 @@ impl Characteristic {
             Characteristic::UiCmd => 0xc3ff0009_1d8b_40fd_a56f_c7bd5d0f3370,
             Characteristic::TapMode => 0x6e400002_b5a3_f393_e0a9_e50e24dcca9e,
             Characteristic::RawSensors => 0x6e400003_b5a3_f393_e0a9_e50e24dcca9e,
+            Characteristic::BatteryLevel => 0x00002a19_0000_1000_8000_00805f9b34fb,
+            Characteristic::ManufacturerName => 0x00002a29_0000_1000_8000_00805f9b34fb,
+            Characteristic::ModelNumber => 0x00002a24_0000_1000_8000_00805f9b34fb,
+            Characteristic::SerialNumber => 0x00002a25_0000_1000_8000_00805f9b34fb,
+            Characteristic::HardwareRevision => 0x00002a27_0000_1000_8000_00805f9b34fb,
+            Characteristic::FirmwareRevision => 0x00002a26_0000_1000_8000_00805f9b34fb,
+            Characteristic::SoftwareRevision => 0x00002a28_0000_1000_8000_00805f9b34fb,
+        }
+    }
+
+    pub fn service(&self) -> Service {
+        match self {
+            Characteristic::TapData
+            | Characteristic::MouseData
+            | Characteristic::AirGestureData
+            | Characteristic::UiCmd => Service::Tap,
+            Characteristic::TapMode | Characteristic::RawSensors => Service::Nus,
+            Characteristic::BatteryLevel => Service::Battery,
+            Characteristic::ManufacturerName
+            | Characteristic::ModelNumber
+            | Characteristic::SerialNumber
+            | Characteristic::HardwareRevision
+            | Characteristic::FirmwareRevision
+            | Characteristic::SoftwareRevision => Service::DeviceInformation,
+        }
+    }
+
+    // GATT properties of the characteristic on a Tap device.
+    pub fn properties(&self) -> CharPropFlags {
+        match self {
+            Characteristic::TapData
+            | Characteristic::MouseData
+            | Characteristic::AirGestureData
+            | Characteristic::RawSensors => CharPropFlags::NOTIFY,
+            Characteristic::UiCmd | Characteristic::TapMode => {
+                CharPropFlags::WRITE | CharPropFlags::WRITE_WITHOUT_RESPONSE
+            }
+            Characteristic::BatteryLevel => CharPropFlags::READ | CharPropFlags::NOTIFY,
+            Characteristic::ManufacturerName
+            | Characteristic::ModelNumber
+            | Characteristic::SerialNumber
+            | Characteristic::HardwareRevision
+            | Characteristic::FirmwareRevision
+            | Characteristic::SoftwareRevision => CharPropFlags::READ,
         }
     }
 
@@ -32,11 +87,11 @@ impl Characteristic {
         )
     }
 
-    pub fn from_uuid(uuid: Uuid) -> Result<Characteristic, Box<dyn Error>> {
+    pub fn from_uuid(uuid: Uuid) -> Result<Characteristic, TapError> {
         Characteristic::iter()
         .find(
             |tap_uuid| tap_uuid.as_uuid() == uuid.as_u128()
         )
-        .ok_or_else(|| "No matching characteristic found.".into())
+        .ok_or_else(|| TapError::Protocol(format!("No matching characteristic found for {:?}", uuid)))
     }
 }
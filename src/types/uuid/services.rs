@@ -1,6 +1,9 @@
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum Service {
     Tap,
-    Nus
+    Nus,
+    Battery,
+    DeviceInformation,
 }
 
 impl Service {
@@ -8,6 +11,9 @@ impl Service {
         match self {
             Service::Tap => 0xc3ff0001_1d8b_40fd_a56f_c7bd5d0f3370,
             Service::Nus => 0x6e400001_b5a3_f393_e0a9_e50e24dcca9e,
+            // Standard GATT services.
+            Service::Battery => 0x0000180f_0000_1000_8000_00805f9b34fb,
+            Service::DeviceInformation => 0x0000180a_0000_1000_8000_00805f9b34fb,
         }
     }
-}
\ No newline at end of file
+}